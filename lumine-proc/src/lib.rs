@@ -29,8 +29,10 @@ pub fn handler_fn(_attr: TokenStream, item: TokenStream) -> TokenStream {
             #function_item
 
             use #crate_path;
-            #visibility fn #name#generics(bot: #context_type, event: #event_type) -> #new_return_type<()> {
-                Box::pin(#new_name(bot, event))
+            #visibility fn #name#generics(bot: #context_type, event: #event_type) -> #new_return_type<lumine::handler::Propagation> {
+                Box::pin(async move {
+                    lumine::handler::IntoPropagation::into_propagation(#new_name(bot, event).await)
+                })
             }
         };
         gen.into()
@@ -38,3 +40,190 @@ pub fn handler_fn(_attr: TokenStream, item: TokenStream) -> TokenStream {
         item
     }
 }
+
+/// Implement `lumine::rule::args::CommandArgs` for a struct with named fields
+///
+/// Fields are positional in declaration order, `Option<T>` ones are optional and a
+/// `Vec<T>` one takes the rest. `#[arg(long)]` makes a field an `--option`, a `bool`
+/// one a switch, and `#[arg(default = "...")]` parses the default when it is missing.
+/// The command is named after the struct in kebab case unless `#[command(name = "...")]`
+/// is given, doc comments of the struct describe it.
+#[proc_macro_derive(Command, attributes(command, arg))]
+pub fn derive_command(item: TokenStream) -> TokenStream {
+    let input: syn::DeriveInput = syn::parse(item).unwrap();
+    let struct_name = &input.ident;
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => panic!("Expected a struct with named fields"),
+    };
+
+    let mut name = kebab_case(&struct_name.to_string());
+    for (key, value) in attr_args(&input.attrs, "command") {
+        match (key.as_str(), value) {
+            ("name", Some(value)) => name = value,
+            _ => panic!("Unknown command attribute `{}`", key),
+        }
+    }
+    let description = input
+        .attrs
+        .iter()
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                lit: syn::Lit::Str(doc),
+                ..
+            })) if path.is_ident("doc") => Some(doc.value().trim().to_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut usage = Vec::new();
+    let mut options = Vec::new();
+    let mut switches = Vec::new();
+    let mut initializers = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut long = false;
+        let mut default = None;
+        for (key, value) in attr_args(&field.attrs, "arg") {
+            match (key.as_str(), value) {
+                ("long", None) => long = true,
+                ("default", Some(value)) => default = Some(value),
+                _ => panic!("Unknown arg attribute `{}`", key),
+            }
+        }
+        let default_tokens = match &default {
+            Some(default) => quote! { Some(#default) },
+            None => quote! { None },
+        };
+
+        let wrapper = type_wrapper(&field.ty);
+        let value = if long {
+            let option = kebab_case(&ident.to_string());
+            match wrapper {
+                Some("bool") => {
+                    usage.push(format!("[--{}]", option));
+                    switches.push(option.clone());
+                    quote! { args.switch(#option) }
+                }
+                Some("Option") => {
+                    usage.push(format!("[--{} <{}>]", option, ident));
+                    options.push(option.clone());
+                    quote! { args.optional_option(#option)? }
+                }
+                _ => {
+                    usage.push(match default {
+                        Some(_) => format!("[--{} <{}>]", option, ident),
+                        None => format!("--{} <{}>", option, ident),
+                    });
+                    options.push(option.clone());
+                    quote! { args.option(#option, #default_tokens)? }
+                }
+            }
+        } else {
+            let positional = ident.to_string();
+            match wrapper {
+                Some("Option") => {
+                    usage.push(format!("[{}]", positional));
+                    quote! { args.optional_positional(#positional)? }
+                }
+                Some("Vec") => {
+                    usage.push(format!("[{}...]", positional));
+                    quote! { args.rest(#positional)? }
+                }
+                _ => {
+                    usage.push(match default {
+                        Some(_) => format!("[{}]", positional),
+                        None => format!("<{}>", positional),
+                    });
+                    quote! { args.positional(#positional, #default_tokens)? }
+                }
+            }
+        };
+        initializers.push(quote! { #ident: #value });
+    }
+    let usage = usage.join(" ");
+
+    let gen = quote! {
+        impl lumine::rule::args::CommandArgs for #struct_name {
+            const NAME: &'static str = #name;
+            const DESCRIPTION: &'static str = #description;
+
+            fn usage() -> String {
+                #usage.to_owned()
+            }
+
+            fn parse(args: &[lumine::rule::command::Word]) -> Result<Self, String> {
+                let mut args = lumine::rule::args::ArgParser::new(
+                    args,
+                    &[#(#options),*],
+                    &[#(#switches),*],
+                )?;
+                let parsed = #struct_name {
+                    #(#initializers,)*
+                };
+                args.finish()?;
+                Ok(parsed)
+            }
+        }
+    };
+    gen.into()
+}
+
+/// `key` and `key = "value"` pairs of `#[name(...)]` attributes
+fn attr_args(attrs: &[syn::Attribute], name: &str) -> Vec<(String, Option<String>)> {
+    let mut args = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(name)) {
+        let list = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list,
+            _ => panic!("Expected #[{}(...)]", name),
+        };
+        for nested in list.nested {
+            match nested {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) => args.push((path_name(&path), None)),
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(value),
+                    ..
+                })) => args.push((path_name(&path), Some(value.value()))),
+                _ => panic!("Expected `key` or `key = \"value\"` in #[{}(...)]", name),
+            }
+        }
+    }
+    args
+}
+
+fn path_name(path: &syn::Path) -> String {
+    quote!(#path).to_string()
+}
+
+/// `bool`, `Option` or `Vec` if the type is one of them
+fn type_wrapper(ty: &syn::Type) -> Option<&'static str> {
+    let segment = match ty {
+        syn::Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    ["bool", "Option", "Vec"]
+        .iter()
+        .find(|wrapper| segment.ident == wrapper)
+        .copied()
+}
+
+fn kebab_case(name: &str) -> String {
+    let mut kebab = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            kebab.push('-');
+        }
+        if c == '_' {
+            kebab.push('-');
+        } else {
+            kebab.extend(c.to_lowercase());
+        }
+    }
+    kebab
+}
@@ -1,10 +1,9 @@
 use std::sync::Arc;
 
-use env_logger;
-use log::info;
+use log::{info, warn};
 use perf_monitor::mem::get_process_memory_info;
 
-use lumine::handler_fn;
+use lumine::{handler_fn, msg};
 use lumine::{
     bot::BotBuilder,
     context::MessageContext,
@@ -26,13 +25,15 @@ async fn meta_handler(_context: Arc<Bot>, event: MetaEvent) {
 async fn message_handler(context: MessageContext, event: MessageEvent) {
     info!("Get message event: {:?}", event);
     if let Ok(r) = get_process_memory_info() {
-        context
-            .send(&format!(
-                "Physical Memory: {}\nVirtual Memory: {}",
-                r.resident_set_size,
-                r.virtual_memory_size
-            ))
+        let result = context
+            .send(msg![
+                format!("Physical Memory: {}\n", r.resident_set_size),
+                format!("Virtual Memory: {}", r.virtual_memory_size),
+            ])
             .await;
+        if let Err(e) = result {
+            warn!("Send failed: {:?}", e);
+        }
     };
 }
 
@@ -1,105 +1,409 @@
 use std::{
+    collections::HashMap,
     fmt::Debug,
+    net::SocketAddr,
     pin::Pin,
-    sync::{atomic::AtomicUsize, Arc},
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
-use anyhow::Result;
 use futures::Future;
-use log::{info, warn};
+use log::{debug, info, warn};
 use tokio::{
-    net::{TcpListener, ToSocketAddrs},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
     runtime::{self, Runtime},
+    sync::{mpsc, Semaphore},
+    time,
 };
 
 use crate::{
-    context::MessageContext,
-    handler,
+    client::ApiClient,
+    context::{MessageContext, RequestContext},
+    error::{Error, Result},
+    handler::{self, DispatchPolicy, Propagation},
     protocol::{
-        event::{message::MessageEvent, meta::MetaEvent, Event},
-        handshake::HandshakeCallback,
+        api::APIItem,
+        event::{
+            message::MessageEvent, meta::MetaEvent, notice::NoticeEvent, request::RequestEvent,
+            Event,
+        },
+        handshake::{self, ClientRole, HandshakeCallback},
+        id::UserId,
+    },
+    rule::{
+        command::{Command, CommandRouter, Prefix},
+        keyword::{KeywordRule, KeywordRuleBuilder},
+        text::{TextMatcher, TextRule, TextRuleBuilder},
+    },
+    shutdown::{ShutdownHandle, ShutdownSignal},
+    transport::{
+        webhook::{self, WebhookEventSource},
+        websocket, ActionSink, EventSource,
     },
-    rule::keyword::{KeywordRule, KeywordRuleBuilder},
 };
+#[cfg(feature = "v12")]
+use crate::{context::V12Context, protocol::v12};
 
 pub trait StaticFn = Sync + Send + 'static;
 
 pub type AsyncFnReturnType<T = ()> = Pin<Box<dyn Future<Output = T> + Send>>;
 
-pub type EventHandlerType = Box<dyn Fn(Arc<Bot>, Event) -> AsyncFnReturnType<()> + StaticFn>;
-pub type MetaHandlerType = Box<dyn Fn(Arc<Bot>, MetaEvent) -> AsyncFnReturnType<()> + StaticFn>;
+pub type EventHandlerType =
+    Box<dyn Fn(Arc<Bot>, Event) -> AsyncFnReturnType<Propagation> + StaticFn>;
+pub type MetaHandlerType =
+    Box<dyn Fn(Arc<Bot>, MetaEvent) -> AsyncFnReturnType<Propagation> + StaticFn>;
+pub type ErrorHandlerType =
+    Box<dyn Fn(Arc<Bot>, Arc<Error>) -> AsyncFnReturnType<Propagation> + StaticFn>;
+pub type ConnectHandlerType =
+    Box<dyn Fn(Arc<Bot>, UserId) -> AsyncFnReturnType<Propagation> + StaticFn>;
+pub type NoticeHandlerType =
+    Box<dyn Fn(Arc<Bot>, NoticeEvent) -> AsyncFnReturnType<Propagation> + StaticFn>;
+pub type RequestHandlerType =
+    Box<dyn Fn(RequestContext, RequestEvent) -> AsyncFnReturnType<Propagation> + StaticFn>;
 pub type MessageHandlerType =
-    Box<dyn Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<()> + StaticFn>;
-// pub type MessageEventHandlerType = Box<dyn Fn(MessageContext, Event) -> AsyncFnReturnType<()> + StaticFn>;
+    Box<dyn Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn>;
+#[cfg(feature = "v12")]
+pub type V12EventHandlerType =
+    Box<dyn Fn(V12Context, v12::Event) -> AsyncFnReturnType<Propagation> + StaticFn>;
+// pub type MessageEventHandlerType = Box<dyn Fn(MessageContext, Event) -> AsyncFnReturnType<Propagation> + StaticFn>;
+
+/// Priority of handlers registered without one, higher runs first
+pub const DEFAULT_PRIORITY: i32 = 0;
+
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const RECONNECT_MIN_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
 
 pub struct BotHandler {
-    pub(crate) event_handler: Vec<EventHandlerType>,
-    pub(crate) meta_handler: Vec<MetaHandlerType>,
-    pub(crate) message_handler: Vec<MessageHandlerType>,
+    pub(crate) event_handler: Vec<(i32, EventHandlerType)>,
+    pub(crate) connect_handler: Vec<ConnectHandlerType>,
+    pub(crate) disconnect_handler: Vec<ConnectHandlerType>,
+    pub(crate) error_handler: Vec<ErrorHandlerType>,
+    pub(crate) meta_handler: Vec<(i32, MetaHandlerType)>,
+    pub(crate) notice_handler: Vec<(i32, NoticeHandlerType)>,
+    pub(crate) request_handler: Vec<(i32, RequestHandlerType)>,
+    pub(crate) message_handler: Vec<(i32, MessageHandlerType)>,
     pub(crate) keyword_handler: KeywordRule,
+    pub(crate) command_handler: CommandRouter,
+    pub(crate) text_handler: TextRule,
+    #[cfg(feature = "v12")]
+    pub(crate) v12_event_handler: Vec<(i32, V12EventHandlerType)>,
 }
 
 pub struct Bot {
     pub(crate) access_token: &'static str,
     pub(crate) entry_point: &'static str,
-    pub(crate) sequence_number: AtomicUsize,
+    pub(crate) secret: &'static str,
+    pub(crate) api_timeout: Duration,
     pub(crate) handler: BotHandler,
+    pub(crate) client: Mutex<Option<ApiClient>>,
+    pub(crate) http_client: Option<ApiClient>,
+    pub(crate) accounts: Mutex<HashMap<UserId, ApiClient>>,
+    /// API connections of split reverse websockets, waiting for their event connections
+    pub(crate) api_connections: Mutex<HashMap<UserId, Arc<dyn ActionSink>>>,
+    pub(crate) shutdown: ShutdownHandle,
+    /// Closes API connections once event connections are done, their handlers may still call actions
+    pub(crate) api_shutdown: ShutdownHandle,
+    pub(crate) shutdown_timeout: Duration,
+    pub(crate) dispatch_policy: DispatchPolicy,
+    /// Caps handlers in flight
+    pub(crate) handler_permits: Option<Arc<Semaphore>>,
 }
 
 impl Bot {
+    /// Handle the events of any transport, actions of handlers go to `sink`
+    ///
+    /// Returns once `source` is closed and the handlers of its events are done.
+    pub async fn serve_transport(
+        self: Arc<Self>,
+        source: impl EventSource,
+        sink: Arc<dyn ActionSink>,
+    ) {
+        handler::serve(self, source, Some(sink), None).await
+    }
+
+    /// Stop the bot from another task or thread, `run*` and `connect*` return once it is done
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Client of the account `self_id`, while it is connected
+    pub fn account(&self, self_id: UserId) -> Option<ApiClient> {
+        self.accounts.lock().unwrap().get(&self_id).cloned()
+    }
+
+    /// Accounts currently connected
+    pub fn accounts(&self) -> Vec<UserId> {
+        self.accounts.lock().unwrap().keys().copied().collect()
+    }
+
+    /// Call an action through the latest connection, or the HTTP API if there is none
+    pub async fn call<I: APIItem>(&self, item: I) -> Result<Option<I::Response>> {
+        let client = self.client.lock().unwrap().clone();
+        match client.or_else(|| self.http_client.clone()) {
+            Some(client) => client.call(item).await,
+            None => Err(Error::NoConnection),
+        }
+    }
+
+    /// Accept reverse websocket connections on `bind_address` until shutdown,
+    /// inside the runtime of the caller
+    pub async fn serve<T: ToSocketAddrs + Debug>(self, bind_address: T) -> Result<()> {
+        let listener = TcpListener::bind(&bind_address).await?;
+        info!("Listening on: {:?}", bind_address);
+        self.serve_with_listener(listener).await
+    }
+
+    /// Like [`serve`](Self::serve), on a listener bound by the caller
+    pub async fn serve_with_listener(self, listener: TcpListener) -> Result<()> {
+        let access_token = self.access_token;
+        let entry_point = self.entry_point;
+        let bot = Arc::new(self);
+        let mut shutdown = bot.shutdown.signal();
+        // connections hold a sender, `recv` returns once all of them are done
+        let (running, mut done) = mpsc::channel::<()>(1);
+        let (api_running, mut api_done) = mpsc::channel::<()>(1);
+
+        while let Some((stream, address)) = accept(&bot, &listener, &mut shutdown).await {
+            info!("Receive connection from: {}", address);
+
+            //TODO: reuse callback
+            let (cb, client_info) = HandshakeCallback::new(access_token, entry_point);
+            let bot = bot.clone();
+            // the role is known after the handshake, hold both until then
+            let running = running.clone();
+            let api_running = api_running.clone();
+
+            // a slow client must not hold up the accept loop
+            tokio::spawn(async move {
+                let handshake = time::timeout(
+                    HANDSHAKE_TIMEOUT,
+                    tokio_tungstenite::accept_hdr_async(stream, cb),
+                );
+                let stream = match bot.shutdown.signal().until(handshake).await {
+                    Some(Ok(Ok(stream))) => stream,
+                    Some(Ok(Err(e))) => {
+                        handler::report_error(&bot, e.into());
+                        return;
+                    }
+                    Some(Err(_)) => {
+                        warn!("Handshake with {} timed out", address);
+                        return;
+                    }
+                    None => return,
+                };
+                let client_info = client_info.await.unwrap_or_default();
+                let _running = match client_info.role {
+                    ClientRole::Api => api_running,
+                    _ => running,
+                };
+                if let Err(e) = websocket::handle_connection(stream, bot.clone(), client_info).await
+                {
+                    handler::report_error(&bot, e);
+                }
+            });
+        }
+
+        info!("Shutting down");
+        drop(running);
+        done.recv().await;
+        bot.api_shutdown.shutdown();
+        drop(api_running);
+        api_done.recv().await;
+
+        Ok(())
+    }
+
     pub fn run_with_runtime<T: ToSocketAddrs + Debug>(
         self,
         runtime: Runtime,
         bind_address: T,
     ) -> Result<()> {
-        runtime.block_on(async move {
-            let try_socket = TcpListener::bind(&bind_address).await;
-            let listener = try_socket.expect("Bind address failed");
-            info!("Listening on: {:?}", bind_address);
+        runtime.block_on(self.serve(bind_address))
+    }
+
+    /// Like [`run`](Self::run), and shut down once `signal` completes,
+    /// such as `tokio::signal::ctrl_c()`
+    pub fn run_until<T: ToSocketAddrs + Debug>(
+        self,
+        bind_address: T,
+        signal: impl Future + Send + 'static,
+    ) -> Result<()> {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()?;
+        let shutdown = self.shutdown_handle();
+        runtime.spawn(async move {
+            signal.await;
+            shutdown.shutdown();
+        });
+        self.run_with_runtime(runtime, bind_address)
+    }
+
+    pub fn run<T: ToSocketAddrs + Debug>(self, bind_address: T) -> Result<()> {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()?;
+        self.run_with_runtime(runtime, bind_address)?;
+        Ok(())
+    }
 
-            let access_token = self.access_token;
-            let entry_point = self.entry_point;
-            let bot = Arc::new(self);
+    /// Receive events from HTTP POST reports on `bind_address` until shutdown,
+    /// they are answered with quick operations
+    pub async fn serve_http<T: ToSocketAddrs + Debug>(self, bind_address: T) -> Result<()> {
+        let listener = TcpListener::bind(&bind_address).await?;
+        info!("Listening on: {:?}", bind_address);
+        self.serve_http_with_listener(listener).await
+    }
 
-            while let Ok((stream, address)) = listener.accept().await {
-                info!("Receive connection from: {}", address);
+    /// Like [`serve_http`](Self::serve_http), on a listener bound by the caller
+    pub async fn serve_http_with_listener(self, listener: TcpListener) -> Result<()> {
+        let bot = Arc::new(self);
+        let (tx, rx) = mpsc::unbounded_channel();
+        let sink = bot.http_client.as_ref().and_then(|c| c.sink());
+        let serve = tokio::spawn(handler::serve(
+            bot.clone(),
+            WebhookEventSource(rx),
+            sink,
+            None,
+        ));
+        let mut shutdown = bot.shutdown.signal();
 
-                //TODO: reuse callback
-                let cb = HandshakeCallback::new(access_token, entry_point);
-                let bot = bot.clone();
+        while let Some((stream, address)) = accept(&bot, &listener, &mut shutdown).await {
+            debug!("Receive connection from: {}", address);
 
-                match tokio_tungstenite::accept_hdr_async(stream, cb).await {
-                    Ok(stream) => {
-                        tokio::spawn(handler::handle_connection(stream, bot));
-                    }
-                    Err(e) => {
-                        warn!("Websocket failure, detail: {:?}", e);
-                        continue;
+            let bot = bot.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Err(e) = webhook::handle_connection(stream, bot.clone(), tx).await {
+                    handler::report_error(&bot, e);
+                }
+            });
+        }
+
+        info!("Shutting down");
+        let _ = serve.await;
+
+        Ok(())
+    }
+
+    pub fn run_http_with_runtime<T: ToSocketAddrs + Debug>(
+        self,
+        runtime: Runtime,
+        bind_address: T,
+    ) -> Result<()> {
+        runtime.block_on(self.serve_http(bind_address))
+    }
+
+    pub fn run_http<T: ToSocketAddrs + Debug>(self, bind_address: T) -> Result<()> {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()?;
+        self.run_http_with_runtime(runtime, bind_address)
+    }
+
+    /// Connect to a forward websocket server until shutdown, reconnecting with
+    /// exponential backoff whenever the connection fails or drops
+    pub async fn serve_connect(self, url: &str) -> Result<()> {
+        let access_token = self.access_token;
+        let bot = Arc::new(self);
+        let mut shutdown = bot.shutdown.signal();
+        let mut delay = RECONNECT_MIN_DELAY;
+
+        loop {
+            let request = handshake::client_request(url, access_token)?;
+            let connecting = tokio_tungstenite::connect_async(request);
+            match shutdown.until(connecting).await {
+                None => break,
+                Some(Ok((stream, _))) => {
+                    info!("Connected to: {}", url);
+                    delay = RECONNECT_MIN_DELAY;
+                    if let Err(e) =
+                        websocket::handle_connection(stream, bot.clone(), Default::default()).await
+                    {
+                        handler::report_error(&bot, e);
                     }
                 }
+                Some(Err(e)) => {
+                    warn!("Connect to {} failed", url);
+                    handler::report_error(&bot, e.into());
+                }
             }
-        });
 
+            if bot.shutdown.is_shutdown() {
+                break;
+            }
+            info!("Reconnect in {:?}", delay);
+            if shutdown.until(time::sleep(delay)).await.is_none() {
+                break;
+            }
+            delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+        }
+
+        info!("Shut down");
         Ok(())
     }
 
-    pub fn run<T: ToSocketAddrs + Debug>(self, bind_address: T) -> Result<()> {
+    pub fn connect_with_runtime(self, runtime: Runtime, url: &str) -> Result<()> {
+        runtime.block_on(self.serve_connect(url))
+    }
+
+    pub fn connect(self, url: &str) -> Result<()> {
         let runtime = runtime::Builder::new_current_thread()
             .enable_io()
-            .build()
-            .unwrap();
-        self.run_with_runtime(runtime, bind_address)?;
-        Ok(())
+            .enable_time()
+            .build()?;
+        self.connect_with_runtime(runtime, url)
+    }
+}
+
+/// Next connection of `listener`, `None` on shutdown
+///
+/// Failures are reported, accepting pauses for a while after one.
+async fn accept(
+    bot: &Arc<Bot>,
+    listener: &TcpListener,
+    shutdown: &mut ShutdownSignal,
+) -> Option<(TcpStream, SocketAddr)> {
+    loop {
+        match shutdown.until(listener.accept()).await? {
+            Ok(accepted) => return Some(accepted),
+            Err(e) => {
+                handler::report_error(bot, e.into());
+                // such as running out of file descriptors, let connections close first
+                shutdown.until(time::sleep(ACCEPT_ERROR_DELAY)).await;
+            }
+        }
     }
 }
 
 pub struct BotBuilder {
     pub(crate) access_token: &'static str,
     pub(crate) entry_point: &'static str,
-    pub(crate) event_handler: Vec<EventHandlerType>,
-    pub(crate) meta_handler: Vec<MetaHandlerType>,
-    pub(crate) message_handler: Vec<MessageHandlerType>,
-    pub(crate) keyword_handler: Vec<(&'static str, MessageHandlerType)>,
+    pub(crate) secret: &'static str,
+    pub(crate) api_timeout: Duration,
+    pub(crate) shutdown_timeout: Duration,
+    pub(crate) dispatch_policy: DispatchPolicy,
+    pub(crate) max_concurrent_handlers: Option<usize>,
+    pub(crate) http_api: Option<(&'static str, &'static str)>,
+    pub(crate) event_handler: Vec<(i32, EventHandlerType)>,
+    pub(crate) connect_handler: Vec<ConnectHandlerType>,
+    pub(crate) disconnect_handler: Vec<ConnectHandlerType>,
+    pub(crate) error_handler: Vec<ErrorHandlerType>,
+    pub(crate) meta_handler: Vec<(i32, MetaHandlerType)>,
+    pub(crate) notice_handler: Vec<(i32, NoticeHandlerType)>,
+    pub(crate) request_handler: Vec<(i32, RequestHandlerType)>,
+    pub(crate) message_handler: Vec<(i32, MessageHandlerType)>,
+    pub(crate) keyword_handler: Vec<(&'static str, i32, MessageHandlerType)>,
+    pub(crate) command_prefixes: Vec<Prefix>,
+    pub(crate) command_handler: Vec<(i32, Command)>,
+    pub(crate) text_handler: Vec<(TextMatcher, i32, MessageHandlerType)>,
+    #[cfg(feature = "v12")]
+    pub(crate) v12_event_handler: Vec<(i32, V12EventHandlerType)>,
 }
 
 impl BotBuilder {
@@ -107,42 +411,281 @@ impl BotBuilder {
         BotBuilder {
             access_token,
             entry_point,
+            secret: "",
+            api_timeout: Duration::from_secs(30),
+            shutdown_timeout: Duration::from_secs(10),
+            dispatch_policy: DispatchPolicy::default(),
+            max_concurrent_handlers: None,
+            http_api: None,
             event_handler: Vec::new(),
+            connect_handler: Vec::new(),
+            disconnect_handler: Vec::new(),
+            error_handler: Vec::new(),
             meta_handler: Vec::new(),
+            notice_handler: Vec::new(),
+            request_handler: Vec::new(),
             message_handler: Vec::new(),
             keyword_handler: Vec::new(),
+            command_prefixes: Vec::new(),
+            command_handler: Vec::new(),
+            text_handler: Vec::new(),
+            #[cfg(feature = "v12")]
+            v12_event_handler: Vec::new(),
         }
     }
 
+    /// Secret to verify `X-Signature` of HTTP POST reports with, empty to skip verification
+    pub fn secret(mut self, secret: &'static str) -> Self {
+        self.secret = secret;
+        self
+    }
+
+    /// HTTP API to call actions through when there is no websocket connection,
+    /// for bots that receive events by HTTP POST
+    pub fn http_api(mut self, url: &'static str, access_token: &'static str) -> Self {
+        self.http_api = Some((url, access_token));
+        self
+    }
+
+    pub fn api_timeout(mut self, timeout: Duration) -> Self {
+        self.api_timeout = timeout;
+        self
+    }
+
+    /// How long handlers in flight may run once their connection ends or on shutdown,
+    /// before the connection is closed and its accounts disconnected
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// How handlers run, each event in its own task by default
+    pub fn dispatch_policy(mut self, policy: DispatchPolicy) -> Self {
+        self.dispatch_policy = policy;
+        self
+    }
+
+    /// Cap on handlers running at the same time, events beyond it wait for a slot
+    ///
+    /// The cap counts events, or handlers under [`DispatchPolicy::Concurrent`].
+    pub fn max_concurrent_handlers(mut self, max: usize) -> Self {
+        self.max_concurrent_handlers = Some(max);
+        self
+    }
+
     pub fn on_event(
+        self,
+        f: impl Fn(Arc<Bot>, Event) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_event_with_priority(DEFAULT_PRIORITY, f)
+    }
+
+    /// Handlers of an event run from the highest priority down, those of the same priority
+    /// in registration order, and a handler returning [`Propagation::Stop`] ends the run
+    pub fn on_event_with_priority(
+        mut self,
+        priority: i32,
+        f: impl Fn(Arc<Bot>, Event) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.event_handler.push((priority, Box::new(f)));
+        self
+    }
+
+    /// Called with every OneBot v12 event
+    #[cfg(feature = "v12")]
+    pub fn on_v12_event(
+        self,
+        f: impl Fn(V12Context, v12::Event) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_v12_event_with_priority(DEFAULT_PRIORITY, f)
+    }
+
+    #[cfg(feature = "v12")]
+    pub fn on_v12_event_with_priority(
+        mut self,
+        priority: i32,
+        f: impl Fn(V12Context, v12::Event) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.v12_event_handler.push((priority, Box::new(f)));
+        self
+    }
+
+    /// Called when an account connects, with its `self_id`
+    pub fn on_connect(
+        mut self,
+        f: impl Fn(Arc<Bot>, UserId) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.connect_handler.push(Box::new(f));
+        self
+    }
+
+    /// Called when the connection of an account drops, with its `self_id`
+    pub fn on_disconnect(
+        mut self,
+        f: impl Fn(Arc<Bot>, UserId) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.disconnect_handler.push(Box::new(f));
+        self
+    }
+
+    /// Called on connection failures and malformed frames, which are logged as well
+    pub fn on_error(
         mut self,
-        f: impl Fn(Arc<Bot>, Event) -> AsyncFnReturnType<()> + StaticFn,
+        f: impl Fn(Arc<Bot>, Arc<Error>) -> AsyncFnReturnType<Propagation> + StaticFn,
     ) -> Self {
-        self.event_handler.push(Box::new(f));
+        self.error_handler.push(Box::new(f));
         self
     }
 
     pub fn on_meta(
+        self,
+        f: impl Fn(Arc<Bot>, MetaEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_meta_with_priority(DEFAULT_PRIORITY, f)
+    }
+
+    pub fn on_meta_with_priority(
+        mut self,
+        priority: i32,
+        f: impl Fn(Arc<Bot>, MetaEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.meta_handler.push((priority, Box::new(f)));
+        self
+    }
+
+    pub fn on_notice(
+        self,
+        f: impl Fn(Arc<Bot>, NoticeEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_notice_with_priority(DEFAULT_PRIORITY, f)
+    }
+
+    pub fn on_notice_with_priority(
+        mut self,
+        priority: i32,
+        f: impl Fn(Arc<Bot>, NoticeEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.notice_handler.push((priority, Box::new(f)));
+        self
+    }
+
+    pub fn on_request(
+        self,
+        f: impl Fn(RequestContext, RequestEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_request_with_priority(DEFAULT_PRIORITY, f)
+    }
+
+    pub fn on_request_with_priority(
         mut self,
-        f: impl Fn(Arc<Bot>, MetaEvent) -> AsyncFnReturnType<()> + StaticFn,
+        priority: i32,
+        f: impl Fn(RequestContext, RequestEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
     ) -> Self {
-        self.meta_handler.push(Box::new(f));
+        self.request_handler.push((priority, Box::new(f)));
         self
     }
 
     pub fn on_message(
+        self,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_message_with_priority(DEFAULT_PRIORITY, f)
+    }
+
+    pub fn on_message_with_priority(
         mut self,
-        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<()> + StaticFn,
+        priority: i32,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
     ) -> Self {
-        self.message_handler.push(Box::new(f));
+        self.message_handler.push((priority, Box::new(f)));
         self
     }
+
     pub fn on_keyword(
+        self,
+        keyword: &'static str,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_keyword_with_priority(keyword, DEFAULT_PRIORITY, f)
+    }
+
+    pub fn on_keyword_with_priority(
         mut self,
         keyword: &'static str,
-        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<()> + StaticFn,
+        priority: i32,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.keyword_handler.push((keyword, priority, Box::new(f)));
+        self
+    }
+
+    pub fn on_starts_with(
+        self,
+        prefix: &'static str,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_text(TextMatcher::starts_with(prefix), f)
+    }
+
+    pub fn on_ends_with(
+        self,
+        suffix: &'static str,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_text(TextMatcher::ends_with(suffix), f)
+    }
+
+    pub fn on_full_match(
+        self,
+        text: &'static str,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_text(TextMatcher::full_match(text), f)
+    }
+
+    /// The captures are in [`MessageContext::captures`], `build` panics on an invalid pattern
+    pub fn on_regex(
+        self,
+        pattern: &'static str,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_text(TextMatcher::regex(pattern), f)
+    }
+
+    /// Every handler whose matcher matches the plain text of a message runs,
+    /// unlike keywords of which only the first one found does
+    pub fn on_text(
+        self,
+        matcher: TextMatcher,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.on_text_with_priority(matcher, DEFAULT_PRIORITY, f)
+    }
+
+    pub fn on_text_with_priority(
+        mut self,
+        matcher: TextMatcher,
+        priority: i32,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
     ) -> Self {
-        self.keyword_handler.push((keyword, Box::new(f)));
+        self.text_handler.push((matcher, priority, Box::new(f)));
+        self
+    }
+
+    /// Add a prefix of command lines, `/` is the only one if none is added
+    pub fn command_prefix(mut self, prefix: Prefix) -> Self {
+        self.command_prefixes.push(prefix);
+        self
+    }
+
+    /// Commands are matched by exact name after a prefix, `help` lists them
+    /// unless a command of that name is registered
+    pub fn on_command(self, command: Command) -> Self {
+        self.on_command_with_priority(DEFAULT_PRIORITY, command)
+    }
+
+    pub fn on_command_with_priority(mut self, priority: i32, command: Command) -> Self {
+        self.command_handler.push((priority, command));
         self
     }
 
@@ -150,19 +693,49 @@ impl BotBuilder {
         let mut keyword_handler_builder = KeywordRuleBuilder::new();
         self.keyword_handler
             .into_iter()
-            .for_each(|(k, f)| keyword_handler_builder.insert(k, f));
+            .for_each(|(k, p, f)| keyword_handler_builder.insert(k, p, f));
         let keyword_handler = keyword_handler_builder.build();
+        let mut text_handler_builder = TextRuleBuilder::new();
+        self.text_handler
+            .into_iter()
+            .for_each(|(m, p, f)| text_handler_builder.insert(m, p, f));
+        let text_handler = text_handler_builder.build();
+        let api_timeout = self.api_timeout;
+        let http_client = self
+            .http_api
+            .map(|(url, token)| ApiClient::http(url, token).timeout(api_timeout));
 
         Bot {
             access_token: self.access_token,
             entry_point: self.entry_point,
-            sequence_number: AtomicUsize::new(0),
+            secret: self.secret,
+            api_timeout: self.api_timeout,
             handler: BotHandler {
                 event_handler: self.event_handler,
+                connect_handler: self.connect_handler,
+                disconnect_handler: self.disconnect_handler,
+                error_handler: self.error_handler,
                 meta_handler: self.meta_handler,
+                notice_handler: self.notice_handler,
+                request_handler: self.request_handler,
                 message_handler: self.message_handler,
                 keyword_handler,
+                command_handler: CommandRouter::new(self.command_prefixes, self.command_handler),
+                text_handler,
+                #[cfg(feature = "v12")]
+                v12_event_handler: self.v12_event_handler,
             },
+            client: Mutex::new(None),
+            http_client,
+            accounts: Mutex::new(HashMap::new()),
+            api_connections: Mutex::new(HashMap::new()),
+            shutdown: ShutdownHandle::new(),
+            api_shutdown: ShutdownHandle::new(),
+            shutdown_timeout: self.shutdown_timeout,
+            dispatch_policy: self.dispatch_policy,
+            handler_permits: self
+                .max_concurrent_handlers
+                .map(|n| Arc::new(Semaphore::new(n))),
         }
     }
 }
@@ -0,0 +1,97 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::time;
+
+#[cfg(feature = "v12")]
+use crate::protocol::v12::Action;
+use crate::{
+    error::{Error, Result},
+    protocol::api::{APIItem, QuickOperation},
+    transport::{http::HttpActionSink, ActionSink},
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends actions through an [`ActionSink`] and waits for their responses
+#[derive(Clone)]
+pub struct ApiClient {
+    sink: Option<Arc<dyn ActionSink>>,
+    /// Set when handling an event that may be answered by a quick operation
+    quick_operation: Option<Arc<Mutex<QuickOperation>>>,
+    timeout: Duration,
+}
+
+impl ApiClient {
+    pub fn new(sink: Arc<dyn ActionSink>) -> Self {
+        ApiClient {
+            sink: Some(sink),
+            quick_operation: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Call actions through the HTTP API of a OneBot implementation, such as
+    /// `http://127.0.0.1:5700`
+    pub fn http(url: impl Into<String>, access_token: impl Into<String>) -> Self {
+        Self::new(Arc::new(HttpActionSink::new(url, access_token)))
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub(crate) fn for_event(
+        sink: Option<Arc<dyn ActionSink>>,
+        quick_operation: Option<Arc<Mutex<QuickOperation>>>,
+        timeout: Duration,
+    ) -> Self {
+        ApiClient {
+            sink,
+            quick_operation,
+            timeout,
+        }
+    }
+
+    pub(crate) fn sink(&self) -> Option<Arc<dyn ActionSink>> {
+        self.sink.clone()
+    }
+
+    pub(crate) fn is_same_connection(&self, other: &ApiClient) -> bool {
+        match (&self.sink, &other.sink) {
+            (Some(a), Some(b)) => Arc::as_ptr(a) as *const () == Arc::as_ptr(b) as *const (),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn quick_operation(&self) -> Option<&Arc<Mutex<QuickOperation>>> {
+        self.quick_operation.as_ref()
+    }
+
+    /// `None` if the implementation queued the action, so its result is unknown
+    pub async fn call<I: APIItem>(&self, item: I) -> Result<Option<I::Response>> {
+        let (action, params) = item.into_api(0).split();
+        self.send(action, params).await
+    }
+
+    /// Call a OneBot v12 action
+    #[cfg(feature = "v12")]
+    pub async fn action<A: Action>(&self, action: A) -> Result<Option<A::Response>> {
+        let params = serde_json::to_value(action)?;
+        self.send(A::NAME.to_owned(), params).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, action: String, params: Value) -> Result<Option<T>> {
+        let sink = self.sink.as_ref().ok_or(Error::NoConnection)?;
+
+        match time::timeout(self.timeout, sink.send_action(action, params)).await {
+            Ok(response) => response?.into_data(),
+            Err(_) => Err(Error::Timeout(self.timeout)),
+        }
+    }
+}
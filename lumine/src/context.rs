@@ -1,62 +1,304 @@
 use std::sync::Arc;
 
-use tokio::sync::mpsc::UnboundedSender;
-use tokio_tungstenite::tungstenite::Message;
-
+#[cfg(feature = "v12")]
+use crate::protocol::v12::Action;
 use crate::{
-    protocol::api::{SendGroupMsg, SendPrivateMsg, API},
+    client::ApiClient,
+    error::{Error, Result},
+    protocol::{
+        api::{
+            APIItem, QuickOperation, SendGroupMsg, SendMsgResponse, SendPrivateMsg,
+            SetFriendAddRequest, SetGroupAddRequest,
+        },
+        event::request::{GroupRequestSubType, RequestEvent},
+        id::{GroupId, UserId},
+        message::Message,
+    },
+    rule::{command::Word, text::Captures},
     Bot,
 };
 
 pub struct MessageContext {
-    pub user_id: i64,
-    pub group_id: Option<i32>,
+    pub user_id: UserId,
+    pub group_id: Option<GroupId>,
     pub bot: Arc<Bot>,
-    sequence_number: usize,
-    sender: UnboundedSender<Message>,
+    /// Words of the command line for command handlers, the command name first
+    pub argv: Vec<Word>,
+    /// Words of `argv` naming the command and its sub-commands
+    command_len: usize,
+    /// Groups captured by the regex rule of the handler
+    pub captures: Captures,
+    client: ApiClient,
 }
 
 impl MessageContext {
     pub fn new(
-        user_id: i64,
-        group_id: Option<i32>,
-        sequence_number: usize,
-        sender: UnboundedSender<Message>,
+        user_id: UserId,
+        group_id: Option<GroupId>,
+        client: ApiClient,
         bot: Arc<Bot>,
     ) -> Self {
         MessageContext {
             user_id,
             group_id,
             bot,
-            sequence_number,
-            sender,
+            argv: Vec::new(),
+            command_len: 0,
+            captures: Captures::default(),
+            client,
         }
     }
 
-    pub async fn send(&self, message: &str) {
-        let api = match self.group_id {
+    pub(crate) fn with_argv(mut self, argv: Vec<Word>, command_len: usize) -> Self {
+        self.argv = argv;
+        self.command_len = command_len;
+        self
+    }
+
+    pub(crate) fn with_captures(mut self, captures: Captures) -> Self {
+        self.captures = captures;
+        self
+    }
+
+    /// Arguments of the command, after its name and sub-commands
+    pub fn args(&self) -> &[Word] {
+        &self.argv[self.command_len..]
+    }
+
+    /// `None` if the message is queued rather than sent
+    pub async fn send(&self, message: impl Into<Message>) -> Result<Option<SendMsgResponse>> {
+        let message = message.into();
+        match self.group_id {
             Some(group_id) => {
                 let params = SendGroupMsg {
                     group_id,
-                    message: message.to_owned(),
+                    message,
+                    auto_escape: false,
                 };
-                API::SendGroupMsg {
-                    params,
-                    echo: self.sequence_number,
-                }
+                self.client.call(params).await
             }
             None => {
                 let params = SendPrivateMsg {
                     user_id: self.user_id,
-                    message: message.to_owned(),
+                    message,
+                    auto_escape: false,
                 };
-                API::SendPrivateMsg {
-                    params,
-                    echo: self.sequence_number,
-                }
+                self.client.call(params).await
             }
+        }
+    }
+
+    /// Reply in the response of an HTTP POST report, or send the message otherwise
+    pub async fn quick_reply(&self, message: impl Into<Message>) -> Result<()> {
+        match self.client.quick_operation() {
+            Some(operation) => {
+                operation.lock().unwrap().reply = Some(message.into());
+                Ok(())
+            }
+            None => self.send(message).await.map(|_| ()),
+        }
+    }
+
+    /// Edit the quick operation answering an HTTP POST report,
+    /// [`Error::NoQuickOperation`] for events of other transports
+    pub fn quick_operation(&self, f: impl FnOnce(&mut QuickOperation)) -> Result<()> {
+        match self.client.quick_operation() {
+            Some(operation) => {
+                f(&mut operation.lock().unwrap());
+                Ok(())
+            }
+            None => Err(Error::NoQuickOperation),
+        }
+    }
+
+    pub async fn call<I: APIItem>(&self, item: I) -> Result<Option<I::Response>> {
+        self.client.call(item).await
+    }
+}
+
+pub struct RequestContext {
+    pub user_id: UserId,
+    pub group_id: Option<GroupId>,
+    pub bot: Arc<Bot>,
+    flag: String,
+    sub_type: Option<GroupRequestSubType>,
+    client: ApiClient,
+}
+
+impl RequestContext {
+    pub fn new(event: &RequestEvent, client: ApiClient, bot: Arc<Bot>) -> Self {
+        let (user_id, group_id, flag, sub_type) = match event {
+            RequestEvent::Friend { user_id, flag, .. } => (*user_id, None, flag, None),
+            RequestEvent::Group {
+                sub_type,
+                group_id,
+                user_id,
+                flag,
+                ..
+            } => (*user_id, Some(*group_id), flag, Some(sub_type.clone())),
         };
+        RequestContext {
+            user_id,
+            group_id,
+            bot,
+            flag: flag.clone(),
+            sub_type,
+            client,
+        }
+    }
+
+    /// Accept the request, `remark` only applies to friend requests
+    pub async fn approve(&self, remark: &str) -> Result<()> {
+        self.handle(true, remark).await
+    }
+
+    /// Refuse the request, `reason` only applies to group requests
+    pub async fn reject(&self, reason: &str) -> Result<()> {
+        self.handle(false, reason).await
+    }
+
+    async fn handle(&self, approve: bool, text: &str) -> Result<()> {
+        if let Some(operation) = self.client.quick_operation() {
+            let mut operation = operation.lock().unwrap();
+            operation.approve = Some(approve);
+            match (approve, &self.sub_type) {
+                (true, None) => operation.remark = Some(text.to_owned()),
+                (false, Some(_)) => operation.reason = Some(text.to_owned()),
+                _ => (),
+            }
+            return Ok(());
+        }
+
+        match self.sub_type {
+            Some(ref sub_type) => {
+                let params = SetGroupAddRequest {
+                    flag: self.flag.clone(),
+                    sub_type: sub_type.clone(),
+                    approve,
+                    reason: if approve { "" } else { text }.to_owned(),
+                };
+                self.client.call(params).await.map(|_| ())
+            }
+            None => {
+                let params = SetFriendAddRequest {
+                    flag: self.flag.clone(),
+                    approve,
+                    remark: if approve { text } else { "" }.to_owned(),
+                };
+                self.client.call(params).await.map(|_| ())
+            }
+        }
+    }
+
+    pub async fn call<I: APIItem>(&self, item: I) -> Result<Option<I::Response>> {
+        self.client.call(item).await
+    }
+}
+
+/// Context of OneBot v12 event handlers
+#[cfg(feature = "v12")]
+pub struct V12Context {
+    pub bot: Arc<Bot>,
+    client: ApiClient,
+}
+
+#[cfg(feature = "v12")]
+impl V12Context {
+    pub fn new(client: ApiClient, bot: Arc<Bot>) -> Self {
+        V12Context { bot, client }
+    }
+
+    /// Call an action through the connection the event came from
+    pub async fn action<A: Action>(&self, action: A) -> Result<Option<A::Response>> {
+        self.client.action(action).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use tokio::runtime;
+
+    use super::*;
+    use crate::{bot::BotBuilder, transport::memory::MemoryActionSink};
+
+    /// The action sent to approve or reject the request of `event`, with `text`
+    fn handle(event: Value, approve: bool, text: &str) -> (String, Value) {
+        let event: RequestEvent = serde_json::from_value(event).unwrap();
+        let sink = Arc::new(MemoryActionSink::new());
+        let bot = Arc::new(BotBuilder::new("", "").build());
+        let ctx = RequestContext::new(&event, ApiClient::new(sink.clone()), bot);
+        runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                match approve {
+                    true => ctx.approve(text).await,
+                    false => ctx.reject(text).await,
+                }
+            })
+            .unwrap();
+
+        let mut actions = sink.actions();
+        assert_eq!(actions.len(), 1);
+        actions.remove(0)
+    }
+
+    fn friend_request() -> Value {
+        json!({ "request_type": "friend", "user_id": 2, "comment": "hi", "flag": "f1" })
+    }
+
+    fn group_request() -> Value {
+        json!({
+            "request_type": "group", "sub_type": "invite", "group_id": 1,
+            "user_id": 2, "comment": "hi", "flag": "g1"
+        })
+    }
+
+    #[test]
+    fn approve_friend() {
+        let (action, params) = handle(friend_request(), true, "buddy");
+        assert_eq!(action, "set_friend_add_request");
+        assert_eq!(
+            params,
+            json!({ "flag": "f1", "approve": true, "remark": "buddy" })
+        );
+    }
+
+    #[test]
+    fn reject_friend() {
+        let (action, params) = handle(friend_request(), false, "no");
+        assert_eq!(action, "set_friend_add_request");
+        assert_eq!(params, json!({ "flag": "f1", "approve": false }));
+    }
+
+    #[test]
+    fn approve_group() {
+        let (action, params) = handle(group_request(), true, "yes");
+        assert_eq!(action, "set_group_add_request");
+        assert_eq!(
+            params,
+            json!({ "flag": "g1", "sub_type": "invite", "approve": true })
+        );
+    }
+
+    #[test]
+    fn reject_group() {
+        let (action, params) = handle(group_request(), false, "full");
+        assert_eq!(action, "set_group_add_request");
+        assert_eq!(
+            params,
+            json!({ "flag": "g1", "sub_type": "invite", "approve": false, "reason": "full" })
+        );
+    }
 
-        self.sender.send(Message::text(api.build())).unwrap();
+    #[test]
+    fn no_quick_operation_outside_reports() {
+        let client = ApiClient::new(Arc::new(MemoryActionSink::new()));
+        let bot = Arc::new(BotBuilder::new("", "").build());
+        let ctx = MessageContext::new(UserId(2), None, client, bot);
+        let result = ctx.quick_operation(|operation| operation.at_sender = Some(true));
+        assert!(matches!(result, Err(Error::NoQuickOperation)));
     }
 }
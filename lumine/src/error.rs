@@ -0,0 +1,114 @@
+use std::{fmt, io, time::Duration};
+
+use tokio_tungstenite::tungstenite;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Socket failure while binding or accepting
+    Io(io::Error),
+    /// HTTP failure
+    Http(hyper::Error),
+    /// The HTTP API answered with a non-success status code
+    HttpStatus(u16),
+    /// The HTTP API url can't form a request
+    InvalidUrl(String),
+    /// Websocket failure
+    Transport(Box<tungstenite::Error>),
+    /// The connection went away before the response arrived
+    ConnectionClosed,
+    /// There is no connection to send actions through
+    NoConnection,
+    /// The event is not an HTTP POST report, so it has no quick operation
+    NoQuickOperation,
+    /// No response arrived within the configured API timeout
+    Timeout(Duration),
+    /// The OneBot implementation rejected the action
+    Retcode {
+        retcode: i64,
+        msg: Option<String>,
+        wording: Option<String>,
+    },
+    /// The response is not what the action promised
+    Deserialize(serde_json::Error),
+    /// A received frame or report is neither a known event nor an API response
+    MalformedFrame {
+        frame: String,
+        error: serde_json::Error,
+    },
+    /// OneBot sends text frames only, holds the length of the binary frame
+    BinaryFrame(usize),
+    /// The access token can't be sent in a header
+    InvalidAccessToken,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Http(e) => write!(f, "http error: {}", e),
+            Error::HttpStatus(status) => write!(f, "http status {}", status),
+            Error::InvalidUrl(url) => write!(f, "invalid url: {}", url),
+            Error::Transport(e) => write!(f, "websocket error: {}", e),
+            Error::ConnectionClosed => write!(f, "connection closed"),
+            Error::NoConnection => write!(f, "no connection to send actions through"),
+            Error::NoQuickOperation => write!(f, "no quick operation outside HTTP POST reports"),
+            Error::Timeout(timeout) => write!(f, "API call timed out after {:?}", timeout),
+            Error::Retcode {
+                retcode,
+                msg,
+                wording,
+            } => {
+                write!(f, "API call failed with retcode {}", retcode)?;
+                match wording.as_ref().or(msg.as_ref()) {
+                    Some(reason) => write!(f, ": {}", reason),
+                    None => Ok(()),
+                }
+            }
+            Error::Deserialize(e) => write!(f, "malformed response: {}", e),
+            Error::MalformedFrame { frame, error } => {
+                write!(f, "malformed frame: {}, frame: {}", error, frame)
+            }
+            Error::BinaryFrame(len) => write!(f, "unexpected binary frame of {} bytes", len),
+            Error::InvalidAccessToken => write!(f, "access token is not a valid header value"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Http(e) => Some(e),
+            Error::Transport(e) => Some(e.as_ref()),
+            Error::Deserialize(e) => Some(e),
+            Error::MalformedFrame { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<hyper::Error> for Error {
+    fn from(e: hyper::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(e: tungstenite::Error) -> Self {
+        Error::Transport(Box::new(e))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Deserialize(e)
+    }
+}
@@ -1,139 +1,527 @@
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fmt::Display,
+    mem,
+    sync::{Arc, Mutex},
+};
 
-use futures::{SinkExt, StreamExt};
+use futures::future::{self, Either};
 use log::{debug, error, info, warn};
-use serde_json::{from_str, from_value, Value};
-use tokio::sync::mpsc;
-use tokio::{net::TcpStream, sync::mpsc::UnboundedSender};
-use tokio_tungstenite::{
-    tungstenite::{Error, Message},
-    WebSocketStream,
+use tokio::{
+    sync::{
+        mpsc,
+        oneshot::{self, error::TryRecvError},
+        OwnedSemaphorePermit,
+    },
+    time,
 };
 
 use crate::{
-    context::MessageContext,
-    protocol::event::{message::MessageEvent, Event},
-    Bot,
+    client::ApiClient,
+    context::{MessageContext, RequestContext},
+    error::Error,
+    protocol::{
+        api::QuickOperation,
+        event::{
+            message::MessageEvent,
+            notice::{NoticeEvent, NotifyEvent},
+            request::RequestEvent,
+            Event,
+        },
+        id::{GroupId, UserId},
+    },
+    rule::command::CommandMatch,
+    transport::{ActionSink, AnyEvent, EventSource},
+    AsyncFnReturnType, Bot,
+};
+#[cfg(feature = "v12")]
+use crate::{
+    context::V12Context,
+    protocol::v12::{self, BotSelf},
 };
 
-async fn dispatcher(bot: &Arc<Bot>, ws_message: String, sender: UnboundedSender<Message>) {
-    let undetermined_message: Value = from_str(&ws_message).unwrap();
-    if undetermined_message.get("post_type").is_some() {
-        match from_value::<Event>(undetermined_message) {
-            Ok(e) => {
-                for f in bot.handler.event_handler.iter() {
-                    f(bot.clone(), e.clone()).await;
-                }
-                match e {
-                    Event::Message { event, .. } => {
-                        let (user_id, group_id) = match event {
-                            MessageEvent::Private {
-                                user_id,
-                                ref message,
-                                ..
-                            } => {
-                                info!("Message from {}: {}", user_id, message);
-                                (user_id, None)
-                            }
-                            MessageEvent::Group {
-                                user_id,
-                                group_id,
-                                ref message,
-                                ..
-                            } => {
-                                info!(
-                                    "Message from {} in group {}: {}",
-                                    user_id, group_id, message
-                                );
-                                (user_id, Some(group_id))
-                            }
-                        };
-                        for f in bot.handler.message_handler.iter() {
-                            let msg_ctx = MessageContext::new(
-                                user_id,
-                                group_id,
-                                bot.sequence_number.fetch_add(1, Ordering::Relaxed),
-                                sender.clone(),
-                                bot.clone(),
-                            );
-                            f(msg_ctx, event.clone()).await;
-                        }
-                        let text_message = match event {
-                            MessageEvent::Private { ref message, .. } => message,
-                            MessageEvent::Group { ref message, .. } => message,
-                        };
-                        let f = bot.handler.keyword_handler.find(text_message);
-                        if let Some(f) = f {
-                            let msg_ctx = MessageContext::new(
-                                user_id,
-                                group_id,
-                                bot.sequence_number.fetch_add(1, Ordering::Relaxed),
-                                sender.clone(),
-                                bot.clone(),
-                            );
-                            f(msg_ctx, event.clone()).await;
-                        }
-                    }
-                    Event::MetaEvent { event, .. } => {
-                        for f in bot.handler.meta_handler.iter() {
-                            f(bot.clone(), event.clone()).await;
-                        }
-                    }
-                    _ => (),
-                }
+/// How the handlers of events run, chosen by [`BotBuilder::dispatch_policy`](crate::bot::BotBuilder::dispatch_policy)
+///
+/// Events never wait for each other unless ordered by conversation, handlers
+/// waiting for API responses don't hold up the connection either way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DispatchPolicy {
+    /// Each event in its own task, its handlers one after another
+    #[default]
+    PerEvent,
+    /// Each handler of each event in its own task
+    Concurrent,
+    /// Like [`PerEvent`](Self::PerEvent), and events of the same private chat or group
+    /// wait for the previous one to be handled
+    PerConversation,
+}
+
+/// Whether the handlers after this one see the event
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Propagation {
+    #[default]
+    Continue,
+    Stop,
+}
+
+/// What handlers may return, `()` lets the event through
+pub trait IntoPropagation {
+    fn into_propagation(self) -> Propagation;
+}
+
+impl IntoPropagation for () {
+    fn into_propagation(self) -> Propagation {
+        Propagation::Continue
+    }
+}
+
+impl IntoPropagation for Propagation {
+    fn into_propagation(self) -> Propagation {
+        self
+    }
+}
+
+/// Errors are logged and let the event through
+impl<T: IntoPropagation, E: Display> IntoPropagation for Result<T, E> {
+    fn into_propagation(self) -> Propagation {
+        match self {
+            Ok(propagation) => propagation.into_propagation(),
+            Err(e) => {
+                error!("Handler failed: {}", e);
+                Propagation::Continue
             }
-            Err(e) => warn!("Unknown message: {:?}", e),
         }
-    } else {
-        //TODO handle api resp
     }
 }
 
-pub(crate) async fn handle_connection(
-    stream: WebSocketStream<TcpStream>,
-    bot: Arc<Bot>,
-) -> Result<(), Error> {
-    let (mut writer, mut reader) = stream.split();
+/// Where an event happens, by the account it belongs to
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Conversation {
+    Private(UserId, UserId),
+    Group(UserId, GroupId),
+    /// By `detail_type` and the id of the user, group or channel
+    #[cfg(feature = "v12")]
+    V12(Option<BotSelf>, &'static str, String),
+}
+
+/// Conversations with an event in flight, keep the number of entries low
+const CONVERSATION_PRUNE_THRESHOLD: usize = 1024;
+
+fn conversation(e: &AnyEvent) -> Option<Conversation> {
+    match e {
+        AnyEvent::V11(e) => v11_conversation(e),
+        #[cfg(feature = "v12")]
+        AnyEvent::V12(e) => v12_conversation(e),
+    }
+}
+
+fn v11_conversation(e: &Event) -> Option<Conversation> {
+    let self_id = e.info().self_id;
+    let conversation = match e {
+        Event::Message { event, .. } => match event {
+            MessageEvent::Private { user_id, .. } => Conversation::Private(self_id, *user_id),
+            MessageEvent::Group { group_id, .. } => Conversation::Group(self_id, *group_id),
+        },
+        Event::Notice { event, .. } => match event {
+            NoticeEvent::GroupUpload { group_id, .. }
+            | NoticeEvent::GroupAdmin { group_id, .. }
+            | NoticeEvent::GroupDecrease { group_id, .. }
+            | NoticeEvent::GroupIncrease { group_id, .. }
+            | NoticeEvent::GroupBan { group_id, .. }
+            | NoticeEvent::GroupRecall { group_id, .. }
+            | NoticeEvent::Notify(NotifyEvent::LuckyKing { group_id, .. })
+            | NoticeEvent::Notify(NotifyEvent::Honor { group_id, .. }) => {
+                Conversation::Group(self_id, *group_id)
+            }
+            NoticeEvent::Notify(NotifyEvent::Poke {
+                group_id, user_id, ..
+            }) => match group_id {
+                Some(group_id) => Conversation::Group(self_id, *group_id),
+                None => Conversation::Private(self_id, *user_id),
+            },
+            NoticeEvent::FriendAdd { user_id } | NoticeEvent::FriendRecall { user_id, .. } => {
+                Conversation::Private(self_id, *user_id)
+            }
+            NoticeEvent::Other { .. } => return None,
+        },
+        Event::Request { event, .. } => match event {
+            RequestEvent::Friend { user_id, .. } => Conversation::Private(self_id, *user_id),
+            RequestEvent::Group { group_id, .. } => Conversation::Group(self_id, *group_id),
+        },
+        Event::MetaEvent { .. } => return None,
+    };
+    Some(conversation)
+}
 
-    let (tx, mut rx) = mpsc::unbounded_channel();
+#[cfg(feature = "v12")]
+fn v12_conversation(e: &v12::Event) -> Option<Conversation> {
+    use v12::event::{MessageEvent, NoticeEvent};
 
-    let write_proc = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            debug!("Send websocket data: {:?}", msg);
-            writer.send(msg).await.unwrap()
+    let (kind, id) = match e {
+        v12::Event::Message { event, .. } => match event {
+            MessageEvent::Private { user_id, .. } => ("private", user_id),
+            MessageEvent::Group { group_id, .. } => ("group", group_id),
+            MessageEvent::Channel { channel_id, .. } => ("channel", channel_id),
+        },
+        v12::Event::Notice { event, .. } => match event {
+            NoticeEvent::FriendIncrease { user_id }
+            | NoticeEvent::FriendDecrease { user_id }
+            | NoticeEvent::PrivateMessageDelete { user_id, .. } => ("private", user_id),
+            NoticeEvent::GroupMemberIncrease { group_id, .. }
+            | NoticeEvent::GroupMemberDecrease { group_id, .. }
+            | NoticeEvent::GroupMessageDelete { group_id, .. } => ("group", group_id),
+            NoticeEvent::ChannelMessageDelete { channel_id, .. } => ("channel", channel_id),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(Conversation::V12(
+        e.info().bot_self.clone(),
+        kind,
+        id.clone(),
+    ))
+}
+
+/// A slot of the in-flight handler cap, if there is one
+async fn permit(bot: &Bot) -> Option<OwnedSemaphorePermit> {
+    match &bot.handler_permits {
+        Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+        None => None,
+    }
+}
+
+/// Log `error` and pass it to the error handlers
+pub(crate) fn report_error(bot: &Arc<Bot>, error: Error) {
+    error!("{}", error);
+    if bot.handler.error_handler.is_empty() {
+        return;
+    }
+
+    let bot = bot.clone();
+    let error = Arc::new(error);
+    tokio::spawn(async move {
+        for f in bot.handler.error_handler.iter() {
+            if f(bot.clone(), error.clone()).await == Propagation::Stop {
+                break;
+            }
         }
     });
+}
 
-    let read_proc = tokio::spawn(async move {
-        loop {
-            if let Some(result) = reader.next().await {
-                debug!("Get websocket data: {:?}", result);
-                match result {
-                    Ok(message) => match message {
-                        Message::Text(text) => dispatcher(&bot, text, tx.clone()).await,
-                        Message::Binary(_) => unimplemented!(),
-                        Message::Ping(frame) => tx.send(Message::Pong(frame)).unwrap(),
-                        Message::Pong(_) => unimplemented!(),
-                        Message::Close(frame) => {
-                            info!("Remote disconnect: {:?}", frame);
-                            break;
-                        }
-                    },
-                    Err(error) => {
-                        error!(
-                            "Error when handle websocket connection, message: {:?}",
-                            error
-                        );
-                        break;
+fn connect(bot: &Arc<Bot>, self_id: UserId, client: &ApiClient) {
+    info!("Account {} connected", self_id);
+    bot.accounts.lock().unwrap().insert(self_id, client.clone());
+
+    let bot = bot.clone();
+    tokio::spawn(async move {
+        for f in bot.handler.connect_handler.iter() {
+            if f(bot.clone(), self_id).await == Propagation::Stop {
+                break;
+            }
+        }
+    });
+}
+
+fn disconnect(bot: &Arc<Bot>, self_id: UserId, client: &ApiClient) {
+    info!("Account {} disconnected", self_id);
+    {
+        let mut accounts = bot.accounts.lock().unwrap();
+        // the account may have reconnected already
+        if matches!(accounts.get(&self_id), Some(c) if c.is_same_connection(client)) {
+            accounts.remove(&self_id);
+        }
+    }
+
+    let bot = bot.clone();
+    tokio::spawn(async move {
+        for f in bot.handler.disconnect_handler.iter() {
+            if f(bot.clone(), self_id).await == Propagation::Stop {
+                break;
+            }
+        }
+    });
+}
+
+/// Dispatch every event of `source` until it closes, actions of handlers go to `sink`
+///
+/// Accounts are registered with `sink` as they show up, by `self_id` from the handshake
+/// or from their events, and unregistered once `source` closes and its handlers are done.
+pub(crate) async fn serve(
+    bot: Arc<Bot>,
+    mut source: impl EventSource,
+    sink: Option<Arc<dyn ActionSink>>,
+    self_id: Option<UserId>,
+) {
+    let client = sink
+        .clone()
+        .map(|sink| ApiClient::new(sink).timeout(bot.api_timeout));
+    let mut accounts = Vec::new();
+
+    if let (Some(self_id), Some(client)) = (self_id, &client) {
+        connect(&bot, self_id, client);
+        accounts.push(self_id);
+    }
+
+    let mut shutdown = bot.shutdown.signal();
+    // every handler holds a sender, `recv` returns once all of them are done
+    let (running, mut done) = mpsc::channel::<()>(1);
+    // the last event of each conversation, resolved once it is handled
+    let mut conversations = HashMap::<_, oneshot::Receiver<()>>::new();
+
+    while let Some(Some(incoming)) = shutdown.until(source.next_event()).await {
+        let incoming = match incoming {
+            Ok(incoming) => incoming,
+            Err(e) => {
+                report_error(&bot, e);
+                continue;
+            }
+        };
+        if let (Some(self_id), Some(client)) = (incoming.event.self_id(), &client) {
+            if !accounts.contains(&self_id) {
+                connect(&bot, self_id, client);
+                accounts.push(self_id);
+            }
+        }
+
+        let previous = match bot.dispatch_policy {
+            DispatchPolicy::PerConversation => match conversation(&incoming.event) {
+                Some(key) => {
+                    if conversations.len() >= CONVERSATION_PRUNE_THRESHOLD {
+                        conversations
+                            .retain(|_, handled| handled.try_recv() == Err(TryRecvError::Empty));
+                    }
+                    let (handled, rx) = oneshot::channel();
+                    Some((conversations.insert(key, rx), handled))
+                }
+                None => None,
+            },
+            _ => None,
+        };
+
+        let bot = bot.clone();
+        let sink = sink.clone();
+        let running = running.clone();
+        // handlers may wait for API responses, which arrive through the source,
+        // so they must not block it
+        tokio::spawn(async move {
+            // dropped once handled, which lets the next event of the conversation go
+            let _handled = match previous {
+                Some((previous, handled)) => {
+                    if let Some(previous) = previous {
+                        let _ = previous.await;
                     }
+                    Some(handled)
                 }
+                None => None,
+            };
+            let _permit = match bot.dispatch_policy {
+                DispatchPolicy::Concurrent => None,
+                _ => permit(&bot).await,
+            };
+
+            let quick_operation = incoming
+                .quick_operation
+                .map(|tx| (tx, Arc::new(Mutex::new(QuickOperation::default()))));
+            let client = ApiClient::for_event(
+                sink,
+                quick_operation.as_ref().map(|(_, o)| o.clone()),
+                bot.api_timeout,
+            );
+
+            dispatcher(bot, incoming.event, client).await;
+
+            if let Some((tx, operation)) = quick_operation {
+                let _ = tx.send(mem::take(&mut *operation.lock().unwrap()));
             }
+            drop(running);
+        });
+    }
+
+    drop(running);
+    let finish = async {
+        if !bot.shutdown.is_shutdown() {
+            // the source is closed, handlers waiting for API responses through it fail
+            let _ = done.recv().await;
+            return;
         }
-    });
+        // keep reading, responses to handlers in flight arrive through the source
+        loop {
+            match future::select(Box::pin(done.recv()), source.next_event()).await {
+                Either::Left(_) => break,
+                Either::Right((Some(Ok(incoming)), _)) => {
+                    debug!("Drop event on shutdown: {:?}", incoming.event)
+                }
+                Either::Right((Some(Err(e)), _)) => report_error(&bot, e),
+                Either::Right((None, pending)) => {
+                    pending.await;
+                    break;
+                }
+            }
+        }
+        source.close().await;
+    };
+    if time::timeout(bot.shutdown_timeout, finish).await.is_err() {
+        warn!(
+            "Handlers still running after {:?}, stop waiting",
+            bot.shutdown_timeout
+        );
+    }
+
+    if let Some(client) = &client {
+        for self_id in accounts {
+            disconnect(&bot, self_id, client);
+        }
+    }
+}
 
-    read_proc.await.unwrap();
-    write_proc.await.unwrap();
+async fn dispatcher(bot: Arc<Bot>, e: AnyEvent, client: ApiClient) {
+    let mut handlers = match e {
+        AnyEvent::V11(e) => v11_handlers(&bot, e, client),
+        #[cfg(feature = "v12")]
+        AnyEvent::V12(e) => {
+            let mut handlers = Vec::new();
+            for (priority, f) in bot.handler.v12_event_handler.iter() {
+                let ctx = V12Context::new(client.clone(), bot.clone());
+                handlers.push((*priority, f(ctx, e.clone())));
+            }
+            handlers
+        }
+    };
 
-    Ok(())
+    // stable, so handlers of the same priority keep their order
+    handlers.sort_by_key(|(priority, _)| Reverse(*priority));
+    run_handlers(&bot, handlers).await;
+}
+
+fn v11_handlers(
+    bot: &Arc<Bot>,
+    e: Event,
+    client: ApiClient,
+) -> Vec<(i32, AsyncFnReturnType<Propagation>)> {
+    let self_id = e.info().self_id;
+    let mut handlers = Vec::new();
+    for (priority, f) in bot.handler.event_handler.iter() {
+        handlers.push((*priority, f(bot.clone(), e.clone())));
+    }
+    match e {
+        Event::Message { event, .. } => {
+            let (user_id, group_id) = match event {
+                MessageEvent::Private {
+                    user_id,
+                    ref message,
+                    ..
+                } => {
+                    info!("Message from {}: {}", user_id, message);
+                    (user_id, None)
+                }
+                MessageEvent::Group {
+                    user_id,
+                    group_id,
+                    ref message,
+                    ..
+                } => {
+                    info!(
+                        "Message from {} in group {}: {}",
+                        user_id, group_id, message
+                    );
+                    (user_id, Some(group_id))
+                }
+            };
+            for (priority, f) in bot.handler.message_handler.iter() {
+                let msg_ctx = MessageContext::new(user_id, group_id, client.clone(), bot.clone());
+                handlers.push((*priority, f(msg_ctx, event.clone())));
+            }
+            if !bot.handler.command_handler.is_empty() {
+                if let Some(matched) = bot.handler.command_handler.find(event.message(), self_id) {
+                    let msg_ctx =
+                        MessageContext::new(user_id, group_id, client.clone(), bot.clone())
+                            .with_argv(matched.argv, matched.command_len);
+                    let handler = match matched.command {
+                        CommandMatch::Handler(f) => f(msg_ctx, event.clone()),
+                        CommandMatch::Help(listing) => Box::pin(async move {
+                            msg_ctx.send(listing).await.map(|_| ()).into_propagation()
+                        }),
+                    };
+                    handlers.push((matched.priority, handler));
+                }
+            }
+            let text_message = event.message().plain_text();
+            let f = bot.handler.keyword_handler.find(&text_message);
+            if let Some((priority, f)) = f {
+                let msg_ctx = MessageContext::new(user_id, group_id, client.clone(), bot.clone());
+                handlers.push((*priority, f(msg_ctx, event.clone())));
+            }
+            if !bot.handler.text_handler.is_empty() {
+                for ((priority, f), captures) in bot.handler.text_handler.find(&text_message) {
+                    let msg_ctx =
+                        MessageContext::new(user_id, group_id, client.clone(), bot.clone())
+                            .with_captures(captures);
+                    handlers.push((*priority, f(msg_ctx, event.clone())));
+                }
+            }
+        }
+        Event::Notice { event, .. } => {
+            for (priority, f) in bot.handler.notice_handler.iter() {
+                handlers.push((*priority, f(bot.clone(), event.clone())));
+            }
+        }
+        Event::Request { event, .. } => {
+            for (priority, f) in bot.handler.request_handler.iter() {
+                let req_ctx = RequestContext::new(&event, client.clone(), bot.clone());
+                handlers.push((*priority, f(req_ctx, event.clone())));
+            }
+        }
+        Event::MetaEvent { event, .. } => {
+            for (priority, f) in bot.handler.meta_handler.iter() {
+                handlers.push((*priority, f(bot.clone(), event.clone())));
+            }
+        }
+    }
+    handlers
+}
+
+/// Run the handlers of an event according to the dispatch policy, highest priority first
+///
+/// Under [`DispatchPolicy::Concurrent`] handlers of the same priority run together,
+/// and a `Stop` from any of them skips the lower priorities.
+async fn run_handlers(bot: &Arc<Bot>, handlers: Vec<(i32, AsyncFnReturnType<Propagation>)>) {
+    if bot.dispatch_policy != DispatchPolicy::Concurrent {
+        for (_, handler) in handlers {
+            if handler.await == Propagation::Stop {
+                break;
+            }
+        }
+        return;
+    }
+
+    let mut handlers = handlers.into_iter().peekable();
+    while let Some((priority, handler)) = handlers.next() {
+        let mut group = vec![handler];
+        while let Some((_, handler)) = handlers.next_if(|(p, _)| *p == priority) {
+            group.push(handler);
+        }
+
+        let tasks: Vec<_> = group
+            .into_iter()
+            .map(|handler| {
+                let bot = bot.clone();
+                tokio::spawn(async move {
+                    let _permit = permit(&bot).await;
+                    handler.await
+                })
+            })
+            .collect();
+        let mut propagation = Propagation::Continue;
+        for task in tasks {
+            match task.await {
+                Ok(Propagation::Stop) => propagation = Propagation::Stop,
+                Ok(Propagation::Continue) => {}
+                Err(e) => error!("Handler failed: {}", e),
+            }
+        }
+        if propagation == Propagation::Stop {
+            break;
+        }
+    }
 }
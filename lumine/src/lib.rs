@@ -1,13 +1,17 @@
 #![feature(trait_alias)]
 
-pub use lumine_proc::handler_fn;
+pub use lumine_proc::{handler_fn, Command};
 
 pub use crate::bot::AsyncFnReturnType;
 pub use crate::bot::Bot;
+pub use crate::error::{Error, Result};
 
 pub mod bot;
+pub mod client;
 pub mod context;
+pub mod error;
 pub mod handler;
 pub mod protocol;
 pub mod rule;
-
+pub mod shutdown;
+pub mod transport;
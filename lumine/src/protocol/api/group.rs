@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{
+    event::message::Anonymous,
+    id::{GroupId, UserId},
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupKick {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    #[serde(default)]
+    pub reject_add_request: bool,
+}
+
+/// Mute a member for `duration` seconds, 0 lifts the mute
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupBan {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    pub duration: i64,
+}
+
+/// Mute an anonymous member, identified by either `anonymous` or `flag`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupAnonymousBan {
+    pub group_id: GroupId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anonymous: Option<Anonymous>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flag: Option<String>,
+    pub duration: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupWholeBan {
+    pub group_id: GroupId,
+    pub enable: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupAdmin {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    pub enable: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupAnonymous {
+    pub group_id: GroupId,
+    pub enable: bool,
+}
+
+/// Set the group card of a member, an empty card removes it
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupCard {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    pub card: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupName {
+    pub group_id: GroupId,
+    pub group_name: String,
+}
+
+/// Leave the group, or dismiss it if the bot is the owner and `is_dismiss` is set
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupLeave {
+    pub group_id: GroupId,
+    #[serde(default)]
+    pub is_dismiss: bool,
+}
+
+/// Set the special title of a member for `duration` seconds, -1 means forever
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetGroupSpecialTitle {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    pub special_title: String,
+    pub duration: i64,
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{
+    event::message::{Role, Sex},
+    id::{GroupId, UserId},
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetLoginInfo {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LoginInfo {
+    pub user_id: UserId,
+    pub nickname: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetStrangerInfo {
+    pub user_id: UserId,
+    #[serde(default)]
+    pub no_cache: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StrangerInfo {
+    pub user_id: UserId,
+    pub nickname: String,
+    pub sex: Sex,
+    pub age: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetFriendList {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FriendInfo {
+    pub user_id: UserId,
+    pub nickname: String,
+    pub remark: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetGroupInfo {
+    pub group_id: GroupId,
+    #[serde(default)]
+    pub no_cache: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GroupInfo {
+    pub group_id: GroupId,
+    pub group_name: String,
+    pub member_count: i32,
+    pub max_member_count: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetGroupList {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetGroupMemberInfo {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    #[serde(default)]
+    pub no_cache: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GroupMemberInfo {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    pub nickname: String,
+    pub card: String,
+    pub sex: Sex,
+    pub age: i32,
+    pub area: String,
+    pub join_time: i32,
+    pub last_sent_time: i32,
+    pub level: String,
+    pub role: Role,
+    pub unfriendly: bool,
+    pub title: String,
+    pub title_expire_time: i32,
+    pub card_changeable: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetGroupMemberList {
+    pub group_id: GroupId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HonorType {
+    Talkative,
+    Performer,
+    Legend,
+    StrongNewbie,
+    Emotion,
+    All,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetGroupHonorInfo {
+    pub group_id: GroupId,
+    #[serde(rename = "type")]
+    pub honor_type: HonorType,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CurrentTalkative {
+    pub user_id: UserId,
+    pub nickname: String,
+    pub avatar: String,
+    pub day_count: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HonorOwner {
+    pub user_id: UserId,
+    pub nickname: String,
+    pub avatar: String,
+    pub description: String,
+}
+
+/// Lists absent from the response are left empty
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GroupHonorInfo {
+    pub group_id: GroupId,
+    pub current_talkative: Option<CurrentTalkative>,
+    #[serde(default)]
+    pub talkative_list: Vec<HonorOwner>,
+    #[serde(default)]
+    pub performer_list: Vec<HonorOwner>,
+    #[serde(default)]
+    pub legend_list: Vec<HonorOwner>,
+    #[serde(default)]
+    pub strong_newbie_list: Vec<HonorOwner>,
+    #[serde(default)]
+    pub emotion_list: Vec<HonorOwner>,
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{
+    event::message::Sender,
+    id::{GroupId, MessageId, UserId},
+    message::Message,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SendPrivateMsg {
+    pub user_id: UserId,
+    pub message: Message,
+    #[serde(default)]
+    pub auto_escape: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SendGroupMsg {
+    pub group_id: GroupId,
+    pub message: Message,
+    #[serde(default)]
+    pub auto_escape: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageType {
+    Private,
+    Group,
+}
+
+/// Send to a user or a group, decided by `message_type` or by which id is present
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SendMsg {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<MessageType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<UserId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<GroupId>,
+    pub message: Message,
+    #[serde(default)]
+    pub auto_escape: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SendMsgResponse {
+    pub message_id: MessageId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeleteMsg {
+    pub message_id: MessageId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetMsg {
+    pub message_id: MessageId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetMsgResponse {
+    pub time: i32,
+    pub message_type: MessageType,
+    pub message_id: MessageId,
+    pub real_id: MessageId,
+    pub sender: Sender,
+    pub message: Message,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetForwardMsg {
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetForwardMsgResponse {
+    pub message: Message,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SendLike {
+    pub user_id: UserId,
+    pub times: i32,
+}
@@ -1,4 +1,16 @@
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+pub use self::{group::*, info::*, message::*, quick::*, request::*, system::*};
+
+pub mod group;
+pub mod info;
+pub mod message;
+pub mod quick;
+pub mod request;
+pub mod system;
 
 // {
 //     "action": "send_private_msg",
@@ -13,39 +25,117 @@ macro_rules! api_item {
     (
         $(#[$a:meta])*
         pub enum API {
-            $($item:ident,)+
+            $($item:ident => $response:ty,)+
         }
     ) => {
         $(#[$a])*
         pub enum API{
             $($item { params: $item, echo: usize },)+
         }
+
+        $(
+            impl APIItem for $item {
+                type Response = $response;
+
+                fn into_api(self, echo: usize) -> API {
+                    API::$item { params: self, echo }
+                }
+            }
+        )+
     };
 }
 
-trait APIItem{}
+pub trait APIItem {
+    /// Type of `data` in the response of this action
+    type Response: DeserializeOwned;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct SendPrivateMsg {
-    pub user_id: i64,
-    pub message: String,
+    fn into_api(self, echo: usize) -> API;
 }
-impl APIItem for SendPrivateMsg{}
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    Async,
+    Failed,
+}
+
+/// Response of an action, matched to its call by `echo`
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct SendGroupMsg {
-    pub group_id: i32,
-    pub message: String,
+pub struct ApiResponse<T = Value> {
+    pub status: Status,
+    pub retcode: i64,
+    #[serde(default)]
+    pub data: T,
+    /// Why the action failed, `message` in OneBot v12
+    #[serde(default, alias = "message", skip_serializing_if = "Option::is_none")]
+    pub msg: Option<String>,
+    /// Description of the failure for users
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wording: Option<String>,
+    pub echo: Option<usize>,
+}
+
+impl ApiResponse {
+    /// Check `retcode` and parse `data` as the response type of the action,
+    /// `None` if the action is queued and its result unknown
+    pub fn into_data<T: DeserializeOwned>(self) -> Result<Option<T>> {
+        match self.retcode {
+            0 => Ok(Some(serde_json::from_value(self.data)?)),
+            // queued, `data` is null
+            1 => Ok(None),
+            retcode => Err(Error::Retcode {
+                retcode,
+                msg: self.msg,
+                wording: self.wording,
+            }),
+        }
+    }
 }
-impl APIItem for SendGroupMsg{}
 
 api_item! {
     #[derive(Serialize, Deserialize, Clone, Debug)]
     #[serde(tag = "action")]
     #[serde(rename_all = "snake_case")]
     pub enum API {
-        SendPrivateMsg,
-        SendGroupMsg,
+        SendPrivateMsg => SendMsgResponse,
+        SendGroupMsg => SendMsgResponse,
+        SendMsg => SendMsgResponse,
+        DeleteMsg => (),
+        GetMsg => GetMsgResponse,
+        GetForwardMsg => GetForwardMsgResponse,
+        SendLike => (),
+        SetGroupKick => (),
+        SetGroupBan => (),
+        SetGroupAnonymousBan => (),
+        SetGroupWholeBan => (),
+        SetGroupAdmin => (),
+        SetGroupAnonymous => (),
+        SetGroupCard => (),
+        SetGroupName => (),
+        SetGroupLeave => (),
+        SetGroupSpecialTitle => (),
+        SetFriendAddRequest => (),
+        SetGroupAddRequest => (),
+        GetLoginInfo => LoginInfo,
+        GetStrangerInfo => StrangerInfo,
+        GetFriendList => Vec<FriendInfo>,
+        GetGroupInfo => GroupInfo,
+        GetGroupList => Vec<GroupInfo>,
+        GetGroupMemberInfo => GroupMemberInfo,
+        GetGroupMemberList => Vec<GroupMemberInfo>,
+        GetGroupHonorInfo => GroupHonorInfo,
+        GetCookies => Cookies,
+        GetCsrfToken => CsrfToken,
+        GetCredentials => Credentials,
+        GetRecord => File,
+        GetImage => File,
+        CanSendImage => Yes,
+        CanSendRecord => Yes,
+        GetStatus => StatusInfo,
+        GetVersionInfo => VersionInfo,
+        SetRestart => (),
+        CleanCache => (),
     }
 }
 
@@ -53,4 +143,61 @@ impl API {
     pub fn build(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
+
+    /// Action name and params, for transports that carry them apart such as HTTP
+    pub fn split(self) -> (String, Value) {
+        let mut api = match serde_json::to_value(self).unwrap() {
+            Value::Object(api) => api,
+            _ => unreachable!("API is always serialized as an object"),
+        };
+        let action = match api.remove("action") {
+            Some(Value::String(action)) => action,
+            _ => unreachable!("API is tagged by action"),
+        };
+        (action, api.remove("params").unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn response(value: Value) -> ApiResponse {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn into_data_ok() {
+        let ok = response(json!({"status": "ok", "retcode": 0, "data": {"message_id": 1}}));
+        let data = ok.into_data::<SendMsgResponse>().unwrap().unwrap();
+        assert_eq!(data.message_id.0, 1);
+    }
+
+    #[test]
+    fn into_data_async() {
+        let queued = response(json!({"status": "async", "retcode": 1, "data": null}));
+        assert!(queued.into_data::<SendMsgResponse>().unwrap().is_none());
+    }
+
+    #[test]
+    fn into_data_failed() {
+        let failed = response(json!({
+            "status": "failed",
+            "retcode": 100,
+            "data": null,
+            "msg": "INVALID_ARGUMENT",
+            "wording": "bad user_id"
+        }));
+        let error = failed.into_data::<()>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "API call failed with retcode 100: bad user_id"
+        );
+        assert!(matches!(
+            error,
+            Error::Retcode { retcode: 100, msg: Some(ref msg), .. } if msg == "INVALID_ARGUMENT"
+        ));
+    }
 }
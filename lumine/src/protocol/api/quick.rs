@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::message::Message;
+
+/// Answer to an HTTP POST event report, applied by the OneBot implementation
+/// without a separate action
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct QuickOperation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply: Option<Message>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_escape: Option<bool>,
+    /// Mention the sender in a group reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at_sender: Option<bool>,
+    /// Recall the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kick: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ban: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ban_duration: Option<i64>,
+    /// Approve or reject a request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approve: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::event::request::GroupRequestSubType;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetFriendAddRequest {
+    pub flag: String,
+    pub approve: bool,
+    /// Only for approved requests, empty to leave it out
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub remark: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SetGroupAddRequest {
+    pub flag: String,
+    pub sub_type: GroupRequestSubType,
+    pub approve: bool,
+    /// Only for rejected requests, empty to leave it out
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reason: String,
+}
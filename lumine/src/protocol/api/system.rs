@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetCookies {
+    #[serde(default)]
+    pub domain: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Cookies {
+    pub cookies: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetCsrfToken {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CsrfToken {
+    pub token: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetCredentials {
+    #[serde(default)]
+    pub domain: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Credentials {
+    pub cookies: String,
+    pub csrf_token: i32,
+}
+
+/// Convert a received record to `out_format`, such as `mp3` or `amr`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetRecord {
+    pub file: String,
+    pub out_format: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetImage {
+    pub file: String,
+}
+
+/// Absolute path of the downloaded file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct File {
+    pub file: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CanSendImage {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CanSendRecord {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Yes {
+    pub yes: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetStatus {}
+
+/// Implementations may report more fields, they are kept in `extra`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StatusInfo {
+    pub online: Option<bool>,
+    pub good: bool,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetVersionInfo {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VersionInfo {
+    pub app_name: String,
+    pub app_version: String,
+    pub protocol_version: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Restart the OneBot implementation after `delay` milliseconds
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SetRestart {
+    #[serde(default)]
+    pub delay: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CleanCache {}
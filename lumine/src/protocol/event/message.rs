@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-// use serde_json::Value;
-
-// use crate::protocol::message::MessageSegment;
+use crate::protocol::{
+    id::{GroupId, MessageId, UserId},
+    message::Message,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "snake_case")]
@@ -42,7 +43,7 @@ pub enum Role {
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Sender {
-    pub user_id: i64,
+    pub user_id: UserId,
     pub nickname: String,
     pub sex: Option<Sex>,
     pub age: Option<i32>,
@@ -59,22 +60,31 @@ pub struct Sender {
 pub enum MessageEvent {
     Private {
         sub_type: PrivateSubType,
-        message_id: i32,
-        user_id: i64,
-        message: String,
+        message_id: MessageId,
+        user_id: UserId,
+        message: Message,
         raw_message: String,
         font: i32,
         sender: Sender,
     },
     Group {
         sub_type: GroupSubType,
-        message_id: i32,
-        group_id: i32,
-        user_id: i64,
+        message_id: MessageId,
+        group_id: GroupId,
+        user_id: UserId,
         anonymous: Option<Anonymous>,
-        message: String,
+        message: Message,
         raw_message: String,
         font: i32,
         sender: Sender,
     },
 }
+
+impl MessageEvent {
+    pub fn message(&self) -> &Message {
+        match self {
+            MessageEvent::Private { message, .. } => message,
+            MessageEvent::Group { message, .. } => message,
+        }
+    }
+}
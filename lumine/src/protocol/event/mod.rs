@@ -1,11 +1,16 @@
 use serde::{Deserialize, Serialize};
 
+use crate::protocol::id::UserId;
+
 pub mod message;
 pub mod meta;
+pub mod notice;
+pub mod request;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct EventInfo {
     pub time: i64,
-    pub self_id: i64,
+    pub self_id: UserId,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -21,10 +26,14 @@ pub enum Event {
     Notice {
         #[serde(flatten)]
         info: EventInfo,
+        #[serde(flatten)]
+        event: notice::NoticeEvent,
     },
     Request {
         #[serde(flatten)]
         info: EventInfo,
+        #[serde(flatten)]
+        event: request::RequestEvent,
     },
     MetaEvent {
         #[serde(flatten)]
@@ -35,3 +44,14 @@ pub enum Event {
         event: meta::MetaEvent,
     },
 }
+
+impl Event {
+    pub fn info(&self) -> &EventInfo {
+        match self {
+            Event::Message { info, .. } => info,
+            Event::Notice { info, .. } => info,
+            Event::Request { info, .. } => info,
+            Event::MetaEvent { info, .. } => info,
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use serde::{de::Error as _, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::protocol::id::{GroupId, MessageId, UserId};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct File {
+    pub id: String,
+    pub name: String,
+    pub size: i64,
+    pub busid: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupAdminSubType {
+    Set,
+    Unset,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupDecreaseSubType {
+    Leave,
+    Kick,
+    KickMe,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupIncreaseSubType {
+    Approve,
+    Invite,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBanSubType {
+    Ban,
+    LiftBan,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HonorType {
+    Talkative,
+    Performer,
+    Emotion,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "sub_type")]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    /// `group_id` is absent for pokes in private chats
+    Poke {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group_id: Option<GroupId>,
+        user_id: UserId,
+        target_id: UserId,
+    },
+    LuckyKing {
+        group_id: GroupId,
+        user_id: UserId,
+        target_id: UserId,
+    },
+    Honor {
+        group_id: GroupId,
+        honor_type: HonorType,
+        user_id: UserId,
+    },
+}
+
+/// A notice of the standard, anything else is kept as [`NoticeEvent::Other`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(remote = "Self")]
+#[serde(tag = "notice_type")]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeEvent {
+    GroupUpload {
+        group_id: GroupId,
+        user_id: UserId,
+        file: File,
+    },
+    GroupAdmin {
+        sub_type: GroupAdminSubType,
+        group_id: GroupId,
+        user_id: UserId,
+    },
+    GroupDecrease {
+        sub_type: GroupDecreaseSubType,
+        group_id: GroupId,
+        operator_id: UserId,
+        user_id: UserId,
+    },
+    GroupIncrease {
+        sub_type: GroupIncreaseSubType,
+        group_id: GroupId,
+        operator_id: UserId,
+        user_id: UserId,
+    },
+    GroupBan {
+        sub_type: GroupBanSubType,
+        group_id: GroupId,
+        operator_id: UserId,
+        user_id: UserId,
+        duration: i64,
+    },
+    FriendAdd {
+        user_id: UserId,
+    },
+    GroupRecall {
+        group_id: GroupId,
+        user_id: UserId,
+        operator_id: UserId,
+        message_id: MessageId,
+    },
+    FriendRecall {
+        user_id: UserId,
+        message_id: MessageId,
+    },
+    Notify(NotifyEvent),
+    /// Extended by the implementation, like `group_card` of go-cqhttp
+    #[serde(skip)]
+    Other {
+        notice_type: String,
+        data: Map<String, Value>,
+    },
+}
+
+impl Serialize for NoticeEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            NoticeEvent::Other { notice_type, data } => {
+                let mut map = serializer.serialize_map(Some(data.len() + 1))?;
+                map.serialize_entry("notice_type", notice_type)?;
+                for (key, value) in data {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            // the derived impl, inherent functions take precedence
+            _ => NoticeEvent::serialize(self, serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for NoticeEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut data = Map::deserialize(deserializer)?;
+        if let Ok(event) = NoticeEvent::deserialize(Value::Object(data.clone())) {
+            return Ok(event);
+        }
+        match data.remove("notice_type") {
+            Some(Value::String(notice_type)) => Ok(NoticeEvent::Other { notice_type, data }),
+            _ => Err(D::Error::missing_field("notice_type")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::protocol::event::Event;
+
+    fn notice(fields: Value) -> NoticeEvent {
+        let mut frame = json!({ "time": 1515204254, "self_id": 10000, "post_type": "notice" });
+        frame
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        match serde_json::from_value(frame).unwrap() {
+            Event::Notice { event, .. } => event,
+            event => panic!("not a notice: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn group_upload() {
+        let event = notice(json!({
+            "notice_type": "group_upload", "group_id": 1, "user_id": 2,
+            "file": { "id": "abc", "name": "a.txt", "size": 3, "busid": 102 }
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::GroupUpload {
+                file: File { size: 3, .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn group_admin() {
+        let event = notice(json!({
+            "notice_type": "group_admin", "sub_type": "set", "group_id": 1, "user_id": 2
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::GroupAdmin {
+                sub_type: GroupAdminSubType::Set,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn group_decrease() {
+        let event = notice(json!({
+            "notice_type": "group_decrease", "sub_type": "kick_me",
+            "group_id": 1, "operator_id": 3, "user_id": 2
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::GroupDecrease {
+                sub_type: GroupDecreaseSubType::KickMe,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn group_increase() {
+        let event = notice(json!({
+            "notice_type": "group_increase", "sub_type": "invite",
+            "group_id": 1, "operator_id": 3, "user_id": 2
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::GroupIncrease {
+                sub_type: GroupIncreaseSubType::Invite,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn group_ban() {
+        let event = notice(json!({
+            "notice_type": "group_ban", "sub_type": "lift_ban",
+            "group_id": 1, "operator_id": 3, "user_id": 2, "duration": 0
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::GroupBan {
+                sub_type: GroupBanSubType::LiftBan,
+                duration: 0,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn friend_add() {
+        let event = notice(json!({ "notice_type": "friend_add", "user_id": 2 }));
+        assert!(matches!(
+            event,
+            NoticeEvent::FriendAdd { user_id: UserId(2) }
+        ));
+    }
+
+    #[test]
+    fn group_recall() {
+        let event = notice(json!({
+            "notice_type": "group_recall", "group_id": 1, "user_id": 2,
+            "operator_id": 3, "message_id": 4
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::GroupRecall {
+                message_id: MessageId(4),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn friend_recall() {
+        let event =
+            notice(json!({ "notice_type": "friend_recall", "user_id": 2, "message_id": 4 }));
+        assert!(matches!(
+            event,
+            NoticeEvent::FriendRecall {
+                message_id: MessageId(4),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn poke() {
+        let event = notice(json!({
+            "notice_type": "notify", "sub_type": "poke", "group_id": 1, "user_id": 2, "target_id": 3
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::Notify(NotifyEvent::Poke {
+                group_id: Some(GroupId(1)),
+                ..
+            })
+        ));
+
+        // go-cqhttp also reports pokes in private chats, with `sender_id`
+        let event = notice(json!({
+            "notice_type": "notify", "sub_type": "poke", "sender_id": 2, "user_id": 2, "target_id": 3
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::Notify(NotifyEvent::Poke {
+                group_id: None,
+                target_id: UserId(3),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn lucky_king() {
+        let event = notice(json!({
+            "notice_type": "notify", "sub_type": "lucky_king", "group_id": 1, "user_id": 2, "target_id": 3
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::Notify(NotifyEvent::LuckyKing { .. })
+        ));
+    }
+
+    #[test]
+    fn honor() {
+        let event = notice(json!({
+            "notice_type": "notify", "sub_type": "honor", "group_id": 1,
+            "honor_type": "talkative", "user_id": 2
+        }));
+        assert!(matches!(
+            event,
+            NoticeEvent::Notify(NotifyEvent::Honor {
+                honor_type: HonorType::Talkative,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn extension_is_other() {
+        let fields = json!({
+            "notice_type": "group_card", "group_id": 1, "user_id": 2,
+            "card_new": "new", "card_old": "old"
+        });
+        let event = notice(fields.clone());
+        match &event {
+            NoticeEvent::Other { notice_type, data } => {
+                assert_eq!(notice_type, "group_card");
+                assert_eq!(data["card_new"], "new");
+                assert!(!data.contains_key("self_id"));
+            }
+            event => panic!("not other: {:?}", event),
+        }
+        assert_eq!(serde_json::to_value(&event).unwrap(), fields);
+
+        // unknown sub types of the standard ones too
+        let event = notice(json!({ "notice_type": "notify", "sub_type": "title", "user_id": 2 }));
+        assert!(matches!(event, NoticeEvent::Other { .. }));
+    }
+
+    #[test]
+    fn standard_round_trip() {
+        let fields = json!({ "notice_type": "friend_add", "user_id": 2 });
+        assert_eq!(
+            serde_json::to_value(notice(fields.clone())).unwrap(),
+            fields
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::id::{GroupId, UserId};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupRequestSubType {
+    Add,
+    Invite,
+}
+
+/// `flag` identifies the request when approving or rejecting it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "request_type")]
+#[serde(rename_all = "snake_case")]
+pub enum RequestEvent {
+    Friend {
+        user_id: UserId,
+        comment: String,
+        flag: String,
+    },
+    Group {
+        sub_type: GroupRequestSubType,
+        group_id: GroupId,
+        user_id: UserId,
+        comment: String,
+        flag: String,
+    },
+}
@@ -1,31 +1,105 @@
 use log::debug;
 
-use tokio_tungstenite::tungstenite::handshake::server::{
-    Callback, ErrorResponse, Request, Response,
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::{
+    client::IntoClientRequest,
+    handshake::{
+        client,
+        server::{Callback, ErrorResponse, Request, Response},
+    },
+    http::HeaderValue,
 };
 
+use crate::{
+    error::{Error, Result},
+    protocol::id::UserId,
+};
+
+/// Request to a forward websocket server, carrying the access token the same way
+/// [`HandshakeCallback`] expects it
+pub(crate) fn client_request(url: &str, access_token: &str) -> Result<client::Request> {
+    let mut request = url.into_client_request()?;
+    if !access_token.is_empty() {
+        let token = HeaderValue::from_str(&format!("Bearer {}", access_token))
+            .map_err(|_| Error::InvalidAccessToken)?;
+        request.headers_mut().insert("Authorization", token);
+    }
+    Ok(request)
+}
+
+/// What a reverse websocket connection carries, from the `X-Client-Role` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ClientRole {
+    /// Only API calls and their responses
+    Api,
+    /// Only events
+    Event,
+    /// Both
+    #[default]
+    Universal,
+}
+
+impl ClientRole {
+    fn from_header(role: &str) -> Option<Self> {
+        match role {
+            "API" => Some(ClientRole::Api),
+            "Event" => Some(ClientRole::Event),
+            "Universal" => Some(ClientRole::Universal),
+            _ => None,
+        }
+    }
+}
+
+/// What the OneBot implementation tells about itself in the handshake
+#[derive(Debug, Default)]
+pub(crate) struct ClientInfo {
+    pub(crate) self_id: Option<UserId>,
+    pub(crate) role: ClientRole,
+}
+
 pub(crate) struct HandshakeCallback {
     access_token: &'static str,
     entry_point: &'static str,
+    client_info: oneshot::Sender<ClientInfo>,
 }
 
 impl HandshakeCallback {
-    pub(crate) fn new(access_token: &'static str, entry_point: &'static str) -> Self {
-        Self {
+    pub(crate) fn new(
+        access_token: &'static str,
+        entry_point: &'static str,
+    ) -> (Self, oneshot::Receiver<ClientInfo>) {
+        let (tx, rx) = oneshot::channel();
+        let callback = Self {
             access_token,
             entry_point,
+            client_info: tx,
+        };
+        (callback, rx)
+    }
+
+    /// Role implied by the path, `entry_point` itself or its `/api` and `/event` children
+    fn path_role(&self, path: &str) -> Option<ClientRole> {
+        let entry_point = self.entry_point.trim_end_matches('/');
+        match path.strip_prefix(entry_point)?.trim_end_matches('/') {
+            "" => Some(ClientRole::Universal),
+            "/api" => Some(ClientRole::Api),
+            "/event" => Some(ClientRole::Event),
+            _ => None,
         }
     }
 }
 
 impl Callback for HandshakeCallback {
     fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
-        if request.uri().path().as_bytes() != self.entry_point.as_bytes() {
-            return Err(Response::builder()
-                .status(404)
-                .body(Some("No such entry point".to_owned()))
-                .unwrap());
-        }
+        let path_role = match self.path_role(request.uri().path()) {
+            Some(role) => role,
+            None => {
+                return Err(Response::builder()
+                    .status(404)
+                    .body(Some("No such entry point".to_owned()))
+                    .unwrap())
+            }
+        };
 
         let headers = request.headers();
         debug!("Handshake headers: {:?}", headers);
@@ -36,6 +110,16 @@ impl Callback for HandshakeCallback {
         };
 
         if user_access_token == self.access_token.as_bytes() {
+            let self_id = headers
+                .get("X-Self-ID")
+                .and_then(|id| id.to_str().ok())
+                .and_then(|id| id.parse().ok());
+            let role = headers
+                .get("X-Client-Role")
+                .and_then(|role| role.to_str().ok())
+                .and_then(ClientRole::from_header)
+                .unwrap_or(path_role);
+            let _ = self.client_info.send(ClientInfo { self_id, role });
             Ok(response)
         } else {
             Err(Response::builder()
@@ -45,3 +129,63 @@ impl Callback for HandshakeCallback {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio_tungstenite::tungstenite::http;
+
+    use super::*;
+
+    /// Client info of a handshake to `path` with `headers`, or the status it was refused with
+    fn handshake(path: &str, headers: &[(&str, &str)]) -> Result<ClientInfo, http::StatusCode> {
+        let (callback, mut client_info) = HandshakeCallback::new("token", "/ws/");
+        let mut request = Request::builder()
+            .uri(path)
+            .header("Authorization", "Bearer token");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = request.body(()).unwrap();
+        match callback.on_request(&request, Response::new(())) {
+            Ok(_) => Ok(client_info.try_recv().unwrap()),
+            Err(response) => Err(response.status()),
+        }
+    }
+
+    #[test]
+    fn path_roles() {
+        let role = |path| handshake(path, &[]).map(|info| info.role);
+        assert_eq!(role("/ws"), Ok(ClientRole::Universal));
+        assert_eq!(role("/ws/"), Ok(ClientRole::Universal));
+        assert_eq!(role("/ws/api"), Ok(ClientRole::Api));
+        assert_eq!(role("/ws/event/"), Ok(ClientRole::Event));
+        assert_eq!(role("/ws/other"), Err(http::StatusCode::NOT_FOUND));
+        assert_eq!(role("/other"), Err(http::StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn role_header_overrides_path() {
+        let info = handshake(
+            "/ws/api",
+            &[("X-Client-Role", "Event"), ("X-Self-ID", "10000")],
+        )
+        .unwrap();
+        assert_eq!(info.role, ClientRole::Event);
+        assert_eq!(info.self_id, Some(UserId(10000)));
+
+        // an unknown role falls back to the path
+        let info = handshake("/ws/api", &[("X-Client-Role", "Other")]).unwrap();
+        assert_eq!(info.role, ClientRole::Api);
+        assert_eq!(info.self_id, None);
+    }
+
+    #[test]
+    fn wrong_access_token() {
+        let (callback, _) = HandshakeCallback::new("other", "/ws");
+        let request = Request::builder().uri("/ws").body(()).unwrap();
+        let response = callback
+            .on_request(&request, Response::new(()))
+            .unwrap_err();
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,112 @@
+use std::{convert::TryFrom, fmt, num::ParseIntError, str::FromStr};
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Ids are numbers, though some implementations send them as strings
+struct IdVisitor;
+
+impl<'de> Visitor<'de> for IdVisitor {
+    type Value = i64;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an integer or a string of one")
+    }
+
+    fn visit_i64<E: de::Error>(self, id: i64) -> Result<i64, E> {
+        Ok(id)
+    }
+
+    fn visit_u64<E: de::Error>(self, id: u64) -> Result<i64, E> {
+        i64::try_from(id).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(id), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, id: &str) -> Result<i64, E> {
+        id.parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(id), &self))
+    }
+}
+
+/// For the id fields OneBot declares as strings
+pub(crate) fn serialize_as_string<T, S>(id: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: fmt::Display,
+    S: Serializer,
+{
+    serializer.collect_str(id)
+}
+
+macro_rules! id {
+    ($($(#[$a:meta])* $name:ident,)+) => {
+        $(
+            $(#[$a])*
+            #[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+            #[serde(transparent)]
+            pub struct $name(pub i64);
+
+            impl<'de> Deserialize<'de> for $name {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    deserializer.deserialize_any(IdVisitor).map($name)
+                }
+            }
+
+            impl fmt::Display for $name {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    self.0.fmt(f)
+                }
+            }
+
+            impl FromStr for $name {
+                type Err = ParseIntError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    s.parse().map($name)
+                }
+            }
+
+            impl From<i64> for $name {
+                fn from(id: i64) -> Self {
+                    $name(id)
+                }
+            }
+
+            impl From<$name> for i64 {
+                fn from(id: $name) -> Self {
+                    id.0
+                }
+            }
+        )+
+    };
+}
+
+id! {
+    /// QQ number of a user, bots included
+    UserId,
+    /// Group number, may exceed `i32`
+    GroupId,
+    MessageId,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn beyond_i32() {
+        let id: GroupId = serde_json::from_value(json!(4294967296_i64)).unwrap();
+        assert_eq!(id, GroupId(4294967296));
+        assert_eq!(serde_json::to_value(id).unwrap(), json!(4294967296_i64));
+    }
+
+    #[test]
+    fn numeric_strings() {
+        let id: UserId = serde_json::from_value(json!("10001000")).unwrap();
+        assert_eq!(id, UserId(10001000));
+        assert!(serde_json::from_value::<UserId>(json!("all")).is_err());
+        assert!(serde_json::from_value::<UserId>(json!(u64::MAX)).is_err());
+    }
+}
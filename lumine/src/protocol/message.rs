@@ -1,49 +1,95 @@
-use std::fmt;
+use std::{
+    env, fmt,
+    path::{Path, PathBuf},
+    slice,
+};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 
-macro_rules! make_cqcode_pattern {
-    (not-first,$final:expr)=>{
-        concat!(
-            ",",
-            stringify!($final),
-            "={}]"
-        )
-    };
-    (not-first,$left:expr,$($right:expr),+)=>{
-        concat!(
-            ",",
-            stringify!($left),
-            "={}",
-            make_cqcode_pattern!(not-first,$($right),+)
-        )
-    };
-    ($type:expr,$($other:expr),+) => {
-        concat!(
-            "[CQ:",
-            stringify!($type),
-            make_cqcode_pattern!(not-first,$($other),+)
-        )
-    };
+use crate::protocol::id::{self, MessageId, UserId};
 
+/// Escape text for the CQ code string format, `,` is escaped as well inside CQ code params
+pub fn escape(s: &str, in_param: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '[' => escaped.push_str("&#91;"),
+            ']' => escaped.push_str("&#93;"),
+            ',' if in_param => escaped.push_str("&#44;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
-/// format_cqcode!(formatter, cqcode_type, param1, param2, ...)
-/// ```
-/// use std::fmt::{Error, Write};
-///
-/// let mut buf = String::new();
-/// let (id, name) = ("123", "qaq");
-/// format_cqcode!(buf, poke, id, name)
-/// assert_eq!(&buf, "[CQ:poke,id=123,name=qaq]");
-/// ```
-macro_rules! format_cqcode {
-    ($fmt:expr,$type:expr,$($other:expr),+) => {
-        write!($fmt,make_cqcode_pattern!($type,$($other),+),$($other),+)
-    };
+pub fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        unescaped.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let (c, len) = [("&amp;", '&'), ("&#91;", '['), ("&#93;", ']'), ("&#44;", ',')]
+            .iter()
+            .find(|(pattern, _)| rest.starts_with(pattern))
+            .map_or(('&', 1), |(pattern, c)| (*c, pattern.len()));
+        unescaped.push(c);
+        rest = &rest[len..];
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ContactType {
+    Qq,
+    Group,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum Music {
+    #[serde(rename = "qq")]
+    Qq { id: String },
+    #[serde(rename = "163")]
+    NetEase { id: String },
+    #[serde(rename = "xm")]
+    Xiami { id: String },
+    #[serde(rename = "custom")]
+    Custom {
+        url: String,
+        audio: String,
+        title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        image: Option<String>,
+    },
+}
+
+/// Node of a forward message, either an existing message or a custom one
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Node {
+    Id {
+        id: String,
+    },
+    Custom {
+        /// A string in OneBot, numbers are accepted as well
+        #[serde(serialize_with = "id::serialize_as_string")]
+        user_id: UserId,
+        nickname: String,
+        content: Message,
+    },
+}
+
+/// Optional fields are `None` when omitted, which leaves them to the OneBot implementation
+///
+/// Numbers and booleans are accepted for string fields, as well as `null` data.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(remote = "Self")]
 #[serde(tag = "type", content = "data")]
 #[serde(rename_all = "snake_case")]
 pub enum MessageSegment {
@@ -52,36 +98,676 @@ pub enum MessageSegment {
     },
     Image {
         file: String,
+        /// `flash` for flash images
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        r#type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        proxy: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<String>,
     },
     Face {
         id: String,
     },
     Poke {
+        r#type: String,
         id: String,
-        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
     },
     Share {
         url: String,
         title: String,
-        content: String,
-        image: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        image: Option<String>,
     },
+    Record {
+        file: String,
+        /// `1` for voice changing
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        magic: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        proxy: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<String>,
+    },
+    Video {
+        file: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        proxy: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<String>,
+    },
+    /// `qq` is a user id, or `all` to mention everyone
+    At {
+        qq: String,
+    },
+    Rps {},
+    Dice {},
+    Shake {},
+    Anonymous {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore: Option<String>,
+    },
+    Contact {
+        r#type: ContactType,
+        id: String,
+    },
+    Location {
+        lat: String,
+        lon: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+    },
+    Music(Music),
+    Reply {
+        id: String,
+    },
+    Forward {
+        id: String,
+    },
+    Node(Node),
+    Xml {
+        data: String,
+    },
+    Json {
+        data: String,
+    },
+    /// Segment of a type unknown to lumine, kept as is
+    #[serde(untagged)]
+    Other {
+        r#type: String,
+        data: Map<String, Value>,
+    },
+}
+
+impl Serialize for MessageSegment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // the derived impl, inherent functions take precedence
+        MessageSegment::serialize(self, serializer)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawSegment {
+    r#type: String,
+    #[serde(default)]
+    data: Option<Map<String, Value>>,
+}
+
+impl<'de> Deserialize<'de> for MessageSegment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let RawSegment { r#type, data } = RawSegment::deserialize(deserializer)?;
+        let data = data.unwrap_or_default();
+        let normalized: Map<_, _> = data
+            .iter()
+            .map(|(k, v)| match v {
+                Value::Number(_) | Value::Bool(_) => (k.clone(), Value::String(v.to_string())),
+                v => (k.clone(), v.clone()),
+            })
+            .collect();
+        let segment = serde_json::json!({ "type": r#type, "data": normalized });
+        match MessageSegment::deserialize(segment) {
+            Ok(MessageSegment::Other { .. }) | Err(_) => Ok(MessageSegment::Other { r#type, data }),
+            Ok(segment) => Ok(segment),
+        }
+    }
+}
+
+/// Where a media file comes from: url, local path or raw bytes
+#[derive(Clone, Debug)]
+pub struct FileSource(pub String);
+
+/// Url, or anything the OneBot implementation accepts as `file`
+impl From<&str> for FileSource {
+    fn from(s: &str) -> Self {
+        FileSource(s.to_owned())
+    }
+}
+
+impl From<String> for FileSource {
+    fn from(s: String) -> Self {
+        FileSource(s)
+    }
+}
+
+/// Relative paths are resolved against the current directory
+impl From<&Path> for FileSource {
+    fn from(path: &Path) -> Self {
+        let absolute;
+        let path = match env::current_dir() {
+            Ok(dir) if path.is_relative() => {
+                absolute = dir.join(path);
+                &absolute
+            }
+            _ => path,
+        };
+        FileSource(format!("file://{}", path.display()))
+    }
+}
+
+impl From<PathBuf> for FileSource {
+    fn from(path: PathBuf) -> Self {
+        path.as_path().into()
+    }
+}
+
+impl From<&[u8]> for FileSource {
+    fn from(bytes: &[u8]) -> Self {
+        FileSource(format!("base64://{}", base64::encode(bytes)))
+    }
+}
+
+impl From<Vec<u8>> for FileSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        bytes.as_slice().into()
+    }
+}
+
+impl MessageSegment {
+    pub fn text(text: impl Into<String>) -> Self {
+        MessageSegment::Text { text: text.into() }
+    }
+
+    pub fn at(user_id: UserId) -> Self {
+        MessageSegment::At {
+            qq: user_id.to_string(),
+        }
+    }
+
+    pub fn at_all() -> Self {
+        MessageSegment::At {
+            qq: "all".to_owned(),
+        }
+    }
+
+    pub fn face(id: i32) -> Self {
+        MessageSegment::Face { id: id.to_string() }
+    }
+
+    pub fn image(file: impl Into<FileSource>) -> Self {
+        MessageSegment::Image {
+            file: file.into().0,
+            r#type: None,
+            url: None,
+            cache: None,
+            proxy: None,
+            timeout: None,
+        }
+    }
+
+    pub fn record(file: impl Into<FileSource>) -> Self {
+        MessageSegment::Record {
+            file: file.into().0,
+            magic: None,
+            url: None,
+            cache: None,
+            proxy: None,
+            timeout: None,
+        }
+    }
+
+    pub fn video(file: impl Into<FileSource>) -> Self {
+        MessageSegment::Video {
+            file: file.into().0,
+            url: None,
+            cache: None,
+            proxy: None,
+            timeout: None,
+        }
+    }
+
+    pub fn reply(message_id: MessageId) -> Self {
+        MessageSegment::Reply {
+            id: message_id.to_string(),
+        }
+    }
 }
 
 impl fmt::Display for MessageSegment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            MessageSegment::Text { text } => write!(f, "{}", text),
-            MessageSegment::Image { file } => format_cqcode!(f, image, file),
-            MessageSegment::Face { id } => format_cqcode!(f, face, id),
-            MessageSegment::Poke { id, name } => format_cqcode!(f, poke, id, name),
-            MessageSegment::Share {
-                url,
-                title,
-                content,
-                image,
-            } => format_cqcode!(f, share, url, title, content, image),
-            // _ => write!(f, "[unknown segment]"),
+        if let MessageSegment::Text { text } = self {
+            return write!(f, "{}", escape(text, false));
         }
+
+        let (segment_type, data) = match serde_json::to_value(self) {
+            Ok(Value::Object(mut raw)) => (raw.remove("type"), raw.remove("data")),
+            _ => return Err(fmt::Error),
+        };
+        match segment_type {
+            Some(Value::String(t)) => write!(f, "[CQ:{}", t)?,
+            _ => return Err(fmt::Error),
+        }
+        if let Some(Value::Object(data)) = data {
+            for (k, v) in data {
+                match v {
+                    Value::String(v) => write!(f, ",{}={}", k, escape(&v, true))?,
+                    Value::Null => (),
+                    v => write!(f, ",{}={}", k, escape(&v.to_string(), true))?,
+                }
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+/// Message in array format, the CQ code string format is accepted when deserializing
+///
+/// Text converted into a message is plain, CQ codes in it are sent as is.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(from = "RawMessage")]
+pub struct Message(pub Vec<MessageSegment>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawMessage {
+    Segments(Vec<MessageSegment>),
+    String(String),
+}
+
+impl From<RawMessage> for Message {
+    fn from(raw: RawMessage) -> Self {
+        match raw {
+            RawMessage::Segments(segments) => Message(segments),
+            RawMessage::String(s) => Message::parse_cqcode(&s),
+        }
+    }
+}
+
+impl Message {
+    pub fn new() -> Self {
+        Message(Vec::new())
+    }
+
+    pub fn push(&mut self, segment: MessageSegment) {
+        self.0.push(segment);
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, MessageSegment> {
+        self.0.iter()
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.push(MessageSegment::text(text));
+        self
+    }
+
+    pub fn at(mut self, user_id: UserId) -> Self {
+        self.push(MessageSegment::at(user_id));
+        self
+    }
+
+    pub fn at_all(mut self) -> Self {
+        self.push(MessageSegment::at_all());
+        self
+    }
+
+    pub fn face(mut self, id: i32) -> Self {
+        self.push(MessageSegment::face(id));
+        self
+    }
+
+    pub fn image(mut self, file: impl Into<FileSource>) -> Self {
+        self.push(MessageSegment::image(file));
+        self
+    }
+
+    pub fn record(mut self, file: impl Into<FileSource>) -> Self {
+        self.push(MessageSegment::record(file));
+        self
+    }
+
+    pub fn video(mut self, file: impl Into<FileSource>) -> Self {
+        self.push(MessageSegment::video(file));
+        self
+    }
+
+    /// Quote `message_id`, it is placed first no matter when it is added
+    pub fn reply(mut self, message_id: MessageId) -> Self {
+        self.0.insert(0, MessageSegment::reply(message_id));
+        self
+    }
+
+    /// Concatenation of all text segments
+    pub fn plain_text(&self) -> String {
+        self.iter()
+            .filter_map(|s| match s {
+                MessageSegment::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Parse the CQ code string format, unknown CQ codes become [`MessageSegment::Other`]
+    pub fn parse_cqcode(s: &str) -> Self {
+        let mut message = Message::new();
+        let mut rest = s;
+
+        while let Some(start) = rest.find("[CQ:") {
+            // `]` never appears escaped in a CQ code, the first one closes it
+            let end = match rest[start..].find(']') {
+                Some(end) => start + end,
+                None => break,
+            };
+            if start > 0 {
+                message.push(MessageSegment::Text {
+                    text: unescape(&rest[..start]),
+                });
+            }
+            message.push(parse_segment(&rest[start + 4..end]));
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            message.push(MessageSegment::Text {
+                text: unescape(rest),
+            });
+        }
+        message
+    }
+}
+
+/// Parse `type,key=value,...` of a CQ code
+fn parse_segment(code: &str) -> MessageSegment {
+    let mut parts = code.split(',');
+    let segment_type = parts.next().unwrap_or_default().to_owned();
+    let data = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.to_owned(), Value::String(unescape(v))))
+        .collect::<Map<_, _>>();
+
+    let mut raw = Map::new();
+    raw.insert("type".to_owned(), Value::String(segment_type.clone()));
+    raw.insert("data".to_owned(), Value::Object(data.clone()));
+    serde_json::from_value(Value::Object(raw)).unwrap_or(MessageSegment::Other {
+        r#type: segment_type,
+        data,
+    })
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.iter().try_for_each(|s| write!(f, "{}", s))
+    }
+}
+
+/// Collection and conversion impls of a message type wrapping a `Vec` of its segments
+macro_rules! message_impls {
+    ($message:ident, $segment:ident) => {
+        impl<'a> IntoIterator for &'a $message {
+            type Item = &'a $segment;
+            type IntoIter = std::slice::Iter<'a, $segment>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.iter()
+            }
+        }
+
+        impl IntoIterator for $message {
+            type Item = $segment;
+            type IntoIter = std::vec::IntoIter<$segment>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.0.into_iter()
+            }
+        }
+
+        impl<T: Into<$message>> std::ops::Add<T> for $message {
+            type Output = $message;
+
+            fn add(mut self, rhs: T) -> Self::Output {
+                self += rhs;
+                self
+            }
+        }
+
+        impl<T: Into<$message>> std::ops::AddAssign<T> for $message {
+            fn add_assign(&mut self, rhs: T) {
+                self.0.extend(rhs.into().0);
+            }
+        }
+
+        impl Extend<$segment> for $message {
+            fn extend<I: IntoIterator<Item = $segment>>(&mut self, iter: I) {
+                self.0.extend(iter);
+            }
+        }
+
+        impl std::iter::FromIterator<$segment> for $message {
+            fn from_iter<I: IntoIterator<Item = $segment>>(iter: I) -> Self {
+                $message(iter.into_iter().collect())
+            }
+        }
+
+        impl From<$segment> for $message {
+            fn from(segment: $segment) -> Self {
+                $message(vec![segment])
+            }
+        }
+
+        impl From<Vec<$segment>> for $message {
+            fn from(segments: Vec<$segment>) -> Self {
+                $message(segments)
+            }
+        }
+
+        /// A single text segment
+        impl From<String> for $message {
+            fn from(text: String) -> Self {
+                $segment::Text { text }.into()
+            }
+        }
+
+        impl From<&str> for $message {
+            fn from(text: &str) -> Self {
+                text.to_owned().into()
+            }
+        }
+    };
+}
+
+#[cfg(feature = "v12")]
+pub(crate) use message_impls;
+
+message_impls!(Message, MessageSegment);
+
+/// Compose a [`Message`] from anything convertible into one
+/// ```
+/// use lumine::{
+///     msg,
+///     protocol::{id::UserId, message::MessageSegment},
+/// };
+///
+/// let message = msg!["hello ", MessageSegment::at(UserId(10001000)), MessageSegment::face(1)];
+/// assert_eq!(message.to_string(), "hello [CQ:at,qq=10001000][CQ:face,id=1]");
+/// ```
+#[macro_export]
+macro_rules! msg {
+    ($($part:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut message = $crate::protocol::message::Message::new();
+        $(message += $part;)*
+        message
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_round_trip() {
+        for s in ["a&b[c]d,e", "&#91;", "&amp;#91;", "&&;[[", "", "&#4"].iter() {
+            assert_eq!(unescape(&escape(s, false)), *s);
+            assert_eq!(unescape(&escape(s, true)), *s);
+        }
+    }
+
+    #[test]
+    fn unescape_once() {
+        assert_eq!(escape("&#91;", false), "&amp;#91;");
+        assert_eq!(unescape("&amp;#91;"), "&#91;");
+        assert_eq!(unescape("&#91;CQ:at,qq=1&#93;"), "[CQ:at,qq=1]");
+    }
+
+    #[test]
+    fn escape_comma_only_in_params() {
+        assert_eq!(escape("a,b", false), "a,b");
+        assert_eq!(escape("a,b", true), "a&#44;b");
+
+        let message = Message::from("a,b").image("x,y");
+        assert_eq!(message.to_string(), "a,b[CQ:image,file=x&#44;y]");
+    }
+
+    #[test]
+    fn text_is_not_parsed_as_segment() {
+        let message = Message::from("[CQ:at,qq=1]");
+        assert_eq!(message.to_string(), "&#91;CQ:at,qq=1&#93;");
+
+        let parsed = Message::parse_cqcode(&message.to_string());
+        assert!(matches!(
+            parsed.0.as_slice(),
+            [MessageSegment::Text { text }] if text == "[CQ:at,qq=1]"
+        ));
+    }
+
+    #[test]
+    fn parse_segments() {
+        let message = Message::parse_cqcode("hi [CQ:at,qq=10001000] &amp;[CQ:face,id=1]");
+        assert!(matches!(
+            message.0.as_slice(),
+            [
+                MessageSegment::Text { text: a },
+                MessageSegment::At { qq },
+                MessageSegment::Text { text: b },
+                MessageSegment::Face { .. },
+            ] if a == "hi " && qq == "10001000" && b == " &"
+        ));
+    }
+
+    #[test]
+    fn parse_unterminated() {
+        let message = Message::parse_cqcode("hi [CQ:at,qq=1");
+        assert!(matches!(
+            message.0.as_slice(),
+            [MessageSegment::Text { text }] if text == "hi [CQ:at,qq=1"
+        ));
+    }
+
+    #[test]
+    fn relative_path_is_absolute() {
+        let FileSource(file) = Path::new("img/a.png").into();
+        let expected = env::current_dir().unwrap().join("img/a.png");
+        assert_eq!(file, format!("file://{}", expected.display()));
+
+        let FileSource(file) = Path::new("/img/a.png").into();
+        assert_eq!(file, "file:///img/a.png");
+    }
+
+    #[test]
+    fn parse_unknown_as_other() {
+        let message = Message::parse_cqcode("[CQ:unknown_type,key=a&#44;b,flag]");
+        match message.0.as_slice() {
+            [MessageSegment::Other { r#type, data }] => {
+                assert_eq!(r#type, "unknown_type");
+                assert_eq!(data.get("key"), Some(&Value::String("a,b".to_owned())));
+                assert_eq!(data.len(), 1);
+            }
+            other => panic!("expected an unknown segment, got {:?}", other),
+        }
+        assert_eq!(message.to_string(), "[CQ:unknown_type,key=a&#44;b]");
+    }
+
+    fn segment(value: Value) -> MessageSegment {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn segments_round_trip() {
+        let segments = [
+            serde_json::json!({ "type": "at", "data": { "qq": "all" } }),
+            serde_json::json!({ "type": "reply", "data": { "id": "123" } }),
+            serde_json::json!({ "type": "record", "data": { "file": "a.amr", "magic": "1" } }),
+            serde_json::json!({ "type": "video", "data": { "file": "http://a/b.mp4" } }),
+            serde_json::json!({ "type": "json", "data": { "data": "{\"app\":\"a\"}" } }),
+            serde_json::json!({ "type": "xml", "data": { "data": "<?xml version='1.0'?>" } }),
+            serde_json::json!({ "type": "forward", "data": { "id": "abc" } }),
+            serde_json::json!({ "type": "node", "data": { "id": "123" } }),
+            serde_json::json!({ "type": "node", "data": {
+                "user_id": "10001000",
+                "nickname": "a",
+                "content": [{ "type": "text", "data": { "text": "hi" } }]
+            } }),
+        ];
+        for value in segments.iter() {
+            let parsed = segment(value.clone());
+            assert!(!matches!(parsed, MessageSegment::Other { .. }), "{}", value);
+            assert_eq!(&serde_json::to_value(&parsed).unwrap(), value);
+
+            // custom nodes only exist in the array format
+            if value["data"].get("content").is_some() {
+                continue;
+            }
+            let message = Message(vec![parsed]);
+            let reparsed = Message::parse_cqcode(&message.to_string());
+            assert_eq!(reparsed.to_string(), message.to_string());
+        }
+    }
+
+    #[test]
+    fn numbers_as_strings() {
+        let at = segment(serde_json::json!({ "type": "at", "data": { "qq": 10000 } }));
+        assert!(matches!(at, MessageSegment::At { qq } if qq == "10000"));
+
+        let face = segment(serde_json::json!({ "type": "face", "data": { "id": 14 } }));
+        assert!(matches!(face, MessageSegment::Face { id } if id == "14"));
+    }
+
+    #[test]
+    fn custom_node() {
+        let node = segment(serde_json::json!({ "type": "node", "data": {
+            "user_id": 10001000,
+            "nickname": "a",
+            "content": "hi"
+        } }));
+        match node {
+            MessageSegment::Node(Node::Custom { user_id, .. }) => {
+                assert_eq!(user_id, UserId(10001000))
+            }
+            other => panic!("expected a custom node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn null_data() {
+        let shake = segment(serde_json::json!({ "type": "shake", "data": null }));
+        assert!(matches!(shake, MessageSegment::Shake {}));
+
+        let dice = segment(serde_json::json!({ "type": "dice" }));
+        assert!(matches!(dice, MessageSegment::Dice {}));
+    }
+
+    #[test]
+    fn unknown_keeps_data() {
+        let value = serde_json::json!({ "type": "mface", "data": { "id": 1, "flag": true } });
+        let unknown = segment(value.clone());
+        assert!(matches!(&unknown, MessageSegment::Other { r#type, .. } if r#type == "mface"));
+        assert_eq!(serde_json::to_value(&unknown).unwrap(), value);
     }
 }
@@ -1,4 +1,7 @@
 pub mod event;
 pub mod handshake;
+pub mod id;
 pub mod message;
-pub mod api;
\ No newline at end of file
+pub mod api;
+#[cfg(feature = "v12")]
+pub mod v12;
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    event::{Event, StatusInfo, VersionInfo},
+    message::Message,
+};
+
+// {
+//     "action": "send_message",
+//     "params": {
+//         "detail_type": "private",
+//         "user_id": "10001000",
+//         "message": [{ "type": "text", "data": { "text": "你好" } }]
+//     },
+//     "echo": "123"
+// }
+
+macro_rules! action {
+    ($($item:ident = $name:literal => $response:ty,)+) => {
+        $(
+            impl Action for $item {
+                const NAME: &'static str = $name;
+                type Response = $response;
+            }
+        )+
+    };
+}
+
+/// A v12 action, serialized as its `params`
+pub trait Action: Serialize {
+    const NAME: &'static str;
+    /// Type of `data` in the response of this action
+    type Response: DeserializeOwned;
+}
+
+action! {
+    GetLatestEvents = "get_latest_events" => Vec<Event>,
+    GetSupportedActions = "get_supported_actions" => Vec<String>,
+    GetStatus = "get_status" => StatusInfo,
+    GetVersion = "get_version" => VersionInfo,
+    SendMessage = "send_message" => SendMessageResponse,
+    DeleteMessage = "delete_message" => (),
+    GetSelfInfo = "get_self_info" => UserInfo,
+    GetUserInfo = "get_user_info" => UserInfo,
+    GetFriendList = "get_friend_list" => Vec<UserInfo>,
+    GetGroupInfo = "get_group_info" => GroupInfo,
+    GetGroupList = "get_group_list" => Vec<GroupInfo>,
+    GetGroupMemberInfo = "get_group_member_info" => UserInfo,
+    GetGroupMemberList = "get_group_member_list" => Vec<UserInfo>,
+    SetGroupName = "set_group_name" => (),
+    LeaveGroup = "leave_group" => (),
+    UploadFile = "upload_file" => UploadFileResponse,
+    GetFile = "get_file" => FileInfo,
+}
+
+/// Events queued since the last poll, for connections without push such as HTTP
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetLatestEvents {
+    /// 0 means no limit
+    #[serde(default)]
+    pub limit: i64,
+    /// Seconds to wait for an event when there is none, 0 returns at once
+    #[serde(default)]
+    pub timeout: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetSupportedActions {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetStatus {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetVersion {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DetailType {
+    Private,
+    Group,
+    Channel,
+}
+
+/// Ids present depend on `detail_type`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SendMessage {
+    pub detail_type: DetailType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    pub message: Message,
+}
+
+impl SendMessage {
+    pub fn private(user_id: impl Into<String>, message: impl Into<Message>) -> Self {
+        SendMessage {
+            detail_type: DetailType::Private,
+            user_id: Some(user_id.into()),
+            group_id: None,
+            guild_id: None,
+            channel_id: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn group(group_id: impl Into<String>, message: impl Into<Message>) -> Self {
+        SendMessage {
+            detail_type: DetailType::Group,
+            user_id: None,
+            group_id: Some(group_id.into()),
+            guild_id: None,
+            channel_id: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn channel(
+        guild_id: impl Into<String>,
+        channel_id: impl Into<String>,
+        message: impl Into<Message>,
+    ) -> Self {
+        SendMessage {
+            detail_type: DetailType::Channel,
+            user_id: None,
+            group_id: None,
+            guild_id: Some(guild_id.into()),
+            channel_id: Some(channel_id.into()),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SendMessageResponse {
+    pub message_id: String,
+    pub time: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeleteMessage {
+    pub message_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetSelfInfo {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetUserInfo {
+    pub user_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetFriendList {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UserInfo {
+    pub user_id: String,
+    pub user_name: String,
+    #[serde(default)]
+    pub user_displayname: String,
+    /// Only for friends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_remark: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetGroupInfo {
+    pub group_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetGroupList {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GroupInfo {
+    pub group_id: String,
+    pub group_name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetGroupMemberInfo {
+    pub group_id: String,
+    pub user_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetGroupMemberList {
+    pub group_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SetGroupName {
+    pub group_id: String,
+    pub group_name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LeaveGroup {
+    pub group_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileType {
+    Url,
+    Path,
+    Data,
+}
+
+/// The field matching `type` carries the file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UploadFile {
+    pub r#type: FileType,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Base64 in JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UploadFileResponse {
+    pub file_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetFile {
+    pub file_id: String,
+    pub r#type: FileType,
+}
+
+/// Same shape as [`UploadFile`], with the field requested by `type`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{message::Message, BotSelf};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EventInfo {
+    pub id: String,
+    pub time: f64,
+    /// Absent in meta events
+    #[serde(rename = "self", default, skip_serializing_if = "Option::is_none")]
+    pub bot_self: Option<BotSelf>,
+    #[serde(default)]
+    pub sub_type: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    Meta {
+        #[serde(flatten)]
+        info: EventInfo,
+        #[serde(flatten)]
+        event: MetaEvent,
+    },
+    Message {
+        #[serde(flatten)]
+        info: EventInfo,
+        #[serde(flatten)]
+        event: MessageEvent,
+    },
+    Notice {
+        #[serde(flatten)]
+        info: EventInfo,
+        #[serde(flatten)]
+        event: NoticeEvent,
+    },
+    Request {
+        #[serde(flatten)]
+        info: EventInfo,
+        #[serde(flatten)]
+        event: RequestEvent,
+    },
+}
+
+impl Event {
+    pub fn info(&self) -> &EventInfo {
+        match self {
+            Event::Meta { info, .. } => info,
+            Event::Message { info, .. } => info,
+            Event::Notice { info, .. } => info,
+            Event::Request { info, .. } => info,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VersionInfo {
+    #[serde(rename = "impl")]
+    pub implementation: String,
+    pub version: String,
+    pub onebot_version: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BotStatus {
+    #[serde(rename = "self")]
+    pub bot_self: BotSelf,
+    pub online: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StatusInfo {
+    pub good: bool,
+    pub bots: Vec<BotStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "detail_type")]
+#[serde(rename_all = "snake_case")]
+pub enum MetaEvent {
+    Connect { version: VersionInfo },
+    Heartbeat { interval: i64 },
+    StatusUpdate { status: StatusInfo },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "detail_type")]
+#[serde(rename_all = "snake_case")]
+pub enum MessageEvent {
+    Private {
+        message_id: String,
+        message: Message,
+        alt_message: String,
+        user_id: String,
+    },
+    Group {
+        message_id: String,
+        message: Message,
+        alt_message: String,
+        group_id: String,
+        user_id: String,
+    },
+    Channel {
+        message_id: String,
+        message: Message,
+        alt_message: String,
+        guild_id: String,
+        channel_id: String,
+        user_id: String,
+    },
+}
+
+impl MessageEvent {
+    pub fn message(&self) -> &Message {
+        match self {
+            MessageEvent::Private { message, .. } => message,
+            MessageEvent::Group { message, .. } => message,
+            MessageEvent::Channel { message, .. } => message,
+        }
+    }
+
+    pub fn user_id(&self) -> &str {
+        match self {
+            MessageEvent::Private { user_id, .. } => user_id,
+            MessageEvent::Group { user_id, .. } => user_id,
+            MessageEvent::Channel { user_id, .. } => user_id,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "detail_type")]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeEvent {
+    FriendIncrease {
+        user_id: String,
+    },
+    FriendDecrease {
+        user_id: String,
+    },
+    PrivateMessageDelete {
+        message_id: String,
+        user_id: String,
+    },
+    GroupMemberIncrease {
+        group_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    GroupMemberDecrease {
+        group_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    GroupMessageDelete {
+        group_id: String,
+        message_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    GuildMemberIncrease {
+        guild_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    GuildMemberDecrease {
+        guild_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    ChannelMessageDelete {
+        guild_id: String,
+        channel_id: String,
+        message_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    /// Extended by the implementation, `detail_type` is prefixed by its platform
+    #[serde(other)]
+    Other,
+}
+
+/// The standard defines no request events, all of them are extensions
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequestEvent {
+    pub detail_type: String,
+    #[serde(flatten)]
+    pub data: Map<String, Value>,
+}
@@ -0,0 +1,138 @@
+use std::slice;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::protocol::message::message_impls;
+
+/// Media segments refer to files uploaded by the `upload_file` action
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum MessageSegment {
+    Text {
+        text: String,
+    },
+    Mention {
+        user_id: String,
+    },
+    MentionAll {},
+    Image {
+        file_id: String,
+    },
+    Voice {
+        file_id: String,
+    },
+    Audio {
+        file_id: String,
+    },
+    Video {
+        file_id: String,
+    },
+    File {
+        file_id: String,
+    },
+    Location {
+        latitude: f64,
+        longitude: f64,
+        title: String,
+        content: String,
+    },
+    Reply {
+        message_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        user_id: Option<String>,
+    },
+    /// Segment extended by the implementation, kept as is
+    #[serde(untagged)]
+    Other {
+        r#type: String,
+        data: Map<String, Value>,
+    },
+}
+
+impl MessageSegment {
+    pub fn text(text: impl Into<String>) -> Self {
+        MessageSegment::Text { text: text.into() }
+    }
+
+    pub fn mention(user_id: impl Into<String>) -> Self {
+        MessageSegment::Mention {
+            user_id: user_id.into(),
+        }
+    }
+
+    pub fn mention_all() -> Self {
+        MessageSegment::MentionAll {}
+    }
+
+    pub fn image(file_id: impl Into<String>) -> Self {
+        MessageSegment::Image {
+            file_id: file_id.into(),
+        }
+    }
+
+    pub fn reply(message_id: impl Into<String>) -> Self {
+        MessageSegment::Reply {
+            message_id: message_id.into(),
+            user_id: None,
+        }
+    }
+}
+
+/// Always a segment array, v12 has no string format
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(transparent)]
+pub struct Message(pub Vec<MessageSegment>);
+
+impl Message {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, segment: MessageSegment) {
+        self.0.push(segment);
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, MessageSegment> {
+        self.0.iter()
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.push(MessageSegment::text(text));
+        self
+    }
+
+    pub fn mention(mut self, user_id: impl Into<String>) -> Self {
+        self.push(MessageSegment::mention(user_id));
+        self
+    }
+
+    pub fn mention_all(mut self) -> Self {
+        self.push(MessageSegment::mention_all());
+        self
+    }
+
+    pub fn image(mut self, file_id: impl Into<String>) -> Self {
+        self.push(MessageSegment::image(file_id));
+        self
+    }
+
+    /// Reply goes first, wherever it is added
+    pub fn reply(mut self, message_id: impl Into<String>) -> Self {
+        self.0.insert(0, MessageSegment::reply(message_id));
+        self
+    }
+
+    /// Concatenation of all text segments
+    pub fn plain_text(&self) -> String {
+        self.iter()
+            .filter_map(|s| match s {
+                MessageSegment::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+message_impls!(Message, MessageSegment);
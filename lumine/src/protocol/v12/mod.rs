@@ -0,0 +1,20 @@
+//! OneBot v12, enabled by the `v12` feature
+//!
+//! Events and segments are modeled apart from v11: IDs are strings, every event
+//! carries `type`/`detail_type`/`sub_type`, and messages are always segment arrays.
+//! Actions are called through [`ApiClient::action`](crate::client::ApiClient::action).
+
+use serde::{Deserialize, Serialize};
+
+pub use self::{action::*, event::Event, message::*};
+
+pub mod action;
+pub mod event;
+pub mod message;
+
+/// The bot account an event or action belongs to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BotSelf {
+    pub platform: String,
+    pub user_id: String,
+}
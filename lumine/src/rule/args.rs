@@ -0,0 +1,172 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::command::Word;
+
+/// Typed arguments of a command, usually derived with `#[derive(Command)]`
+///
+/// Register them with [`Command::with_args`](super::command::Command::with_args).
+pub trait CommandArgs: Sized {
+    /// Name of the command
+    const NAME: &'static str;
+    /// Shown in the help listing
+    const DESCRIPTION: &'static str = "";
+
+    /// Arguments as shown after the command name, like `<title> [--priority <priority>]`
+    fn usage() -> String;
+
+    /// Parse the arguments after the command name and its sub-commands
+    fn parse(args: &[Word]) -> Result<Self, String>;
+}
+
+/// Arguments split into positionals and `--options`, taken field by field by derived
+/// [`CommandArgs`]
+///
+/// `--name value` and `--name=value` set an option, a switch takes no value,
+/// and everything after `--` is positional. An @mention parses as the id of the user.
+pub struct ArgParser {
+    positionals: Vec<Word>,
+    options: Vec<(String, Option<Word>)>,
+}
+
+impl ArgParser {
+    /// `options` are the known option names without `--`, `switches` those taking no value
+    pub fn new(args: &[Word], options: &[&str], switches: &[&str]) -> Result<Self, String> {
+        let mut positionals = Vec::new();
+        let mut parsed = Vec::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let option = match arg.as_text().and_then(|arg| arg.strip_prefix("--")) {
+                Some("") => {
+                    positionals.extend(args.cloned());
+                    break;
+                }
+                Some(option) => option,
+                None => {
+                    positionals.push(arg.clone());
+                    continue;
+                }
+            };
+            let (name, value) = match option.find('=') {
+                Some(i) => (&option[..i], Some(Word::Text(option[i + 1..].to_owned()))),
+                None => (option, None),
+            };
+
+            if switches.contains(&name) {
+                if value.is_some() {
+                    return Err(format!("--{} takes no value", name));
+                }
+                parsed.push((name.to_owned(), None));
+            } else if options.contains(&name) {
+                let value = match value {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| format!("--{} needs a value", name))?,
+                };
+                parsed.push((name.to_owned(), Some(value)));
+            } else {
+                return Err(format!("unknown option --{}", name));
+            }
+        }
+
+        Ok(ArgParser {
+            positionals,
+            options: parsed,
+        })
+    }
+
+    /// The next positional, or `default` if there is none left
+    pub fn positional<T>(&mut self, name: &str, default: Option<&str>) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.optional_positional(name)? {
+            Some(value) => Ok(value),
+            None => match default {
+                Some(default) => parse(name, &Word::Text(default.to_owned())),
+                None => Err(format!("missing <{}>", name)),
+            },
+        }
+    }
+
+    pub fn optional_positional<T>(&mut self, name: &str) -> Result<Option<T>, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        if self.positionals.is_empty() {
+            return Ok(None);
+        }
+        parse(name, &self.positionals.remove(0)).map(Some)
+    }
+
+    /// Every positional left
+    pub fn rest<T>(&mut self, name: &str) -> Result<Vec<T>, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.positionals
+            .drain(..)
+            .map(|value| parse(name, &value))
+            .collect()
+    }
+
+    /// The last value of `--name`, or `default` if it is not given
+    pub fn option<T>(&mut self, name: &str, default: Option<&str>) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.optional_option(name)? {
+            Some(value) => Ok(value),
+            None => match default {
+                Some(default) => parse(name, &Word::Text(default.to_owned())),
+                None => Err(format!("missing --{}", name)),
+            },
+        }
+    }
+
+    pub fn optional_option<T>(&mut self, name: &str) -> Result<Option<T>, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self
+            .options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.as_ref());
+        value.map(|value| parse(name, value)).transpose()
+    }
+
+    /// Whether `--name` is given
+    pub fn switch(&mut self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    /// Fail on positionals nobody took
+    pub fn finish(self) -> Result<(), String> {
+        match self.positionals.first() {
+            Some(value) => Err(format!("unexpected argument '{}'", value)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parse `word`, a mention as the id of the user
+fn parse<T>(name: &str, word: &Word) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let parsed = match word {
+        Word::Text(text) => text.parse(),
+        Word::Mention(user_id) => user_id.to_string().parse(),
+    };
+    parsed.map_err(|e| format!("invalid value '{}' for {}: {}", word, name, e))
+}
@@ -0,0 +1,409 @@
+use std::fmt::{self, Write};
+
+use super::args::CommandArgs;
+use crate::{
+    bot::{MessageHandlerType, StaticFn, DEFAULT_PRIORITY},
+    context::MessageContext,
+    handler::{IntoPropagation, Propagation},
+    protocol::{
+        event::message::MessageEvent,
+        id::UserId,
+        message::{Message, MessageSegment},
+    },
+    AsyncFnReturnType,
+};
+
+const HELP: &str = "help";
+
+/// What a command line starts with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prefix {
+    /// Text right before the command name, like `/` or `!`
+    Text(&'static str),
+    /// An @mention of the bot, optionally followed by one of the text prefixes
+    Mention,
+}
+
+/// A command matched by its exact name or an alias, with optional sub-commands
+pub struct Command {
+    name: &'static str,
+    aliases: Vec<&'static str>,
+    description: &'static str,
+    usage: String,
+    handler: Option<MessageHandlerType>,
+    subcommands: Vec<Command>,
+}
+
+impl Command {
+    pub fn new(name: &'static str) -> Self {
+        Command {
+            name,
+            aliases: Vec::new(),
+            description: "",
+            usage: String::new(),
+            handler: None,
+            subcommands: Vec::new(),
+        }
+    }
+
+    pub fn alias(mut self, alias: &'static str) -> Self {
+        self.aliases.push(alias);
+        self
+    }
+
+    /// Shown in the help listing
+    pub fn description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// Without a handler, the command lists its sub-commands
+    pub fn handler(
+        mut self,
+        f: impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        self.handler = Some(Box::new(f));
+        self
+    }
+
+    /// A command named after `T`, whose handler gets the arguments parsed as `T`
+    ///
+    /// Arguments that fail to parse are answered with the usage of the command.
+    pub fn with_args<T: CommandArgs + Send + 'static>(
+        f: impl Fn(MessageContext, T) -> AsyncFnReturnType<Propagation> + StaticFn,
+    ) -> Self {
+        let mut command = Command::new(T::NAME).description(T::DESCRIPTION);
+        command.usage = T::usage();
+        command.handler(move |ctx, _| match T::parse(ctx.args()) {
+            Ok(args) => f(ctx, args),
+            Err(e) => Box::pin(async move {
+                let command = ctx.argv[..ctx.argv.len() - ctx.args().len()]
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let usage = format!("{}\nUsage: {} {}", e, command, T::usage());
+                ctx.send(usage.trim_end())
+                    .await
+                    .map(|_| ())
+                    .into_propagation()
+            }),
+        })
+    }
+
+    /// Matched by the word after the name of this command
+    pub fn subcommand(mut self, command: Command) -> Self {
+        self.subcommands.push(command);
+        self
+    }
+
+    fn matches(&self, word: &str) -> bool {
+        self.name == word || self.aliases.contains(&word)
+    }
+
+    fn help(&self, path: &str, listing: &mut String) {
+        let path = format!("{}{}", path, self.name);
+        let _ = write!(listing, "\n{}", path);
+        if !self.usage.is_empty() {
+            let _ = write!(listing, " {}", self.usage);
+        }
+        for alias in self.aliases.iter() {
+            let _ = write!(listing, ", {}", alias);
+        }
+        if !self.description.is_empty() {
+            let _ = write!(listing, " - {}", self.description);
+        }
+        for command in self.subcommands.iter() {
+            command.help(&format!("{} ", path), listing);
+        }
+    }
+}
+
+/// What a command line resolved to
+pub(crate) enum CommandMatch<'a> {
+    Handler(&'a MessageHandlerType),
+    /// The help listing to reply with
+    Help(String),
+}
+
+/// A matched command line, `argv` is split from the message after the prefix
+pub(crate) struct Matched<'a> {
+    pub(crate) priority: i32,
+    pub(crate) command: CommandMatch<'a>,
+    pub(crate) argv: Vec<Word>,
+    /// Words of `argv` naming the command and its sub-commands
+    pub(crate) command_len: usize,
+}
+
+pub(crate) struct CommandRouter {
+    prefixes: Vec<Prefix>,
+    commands: Vec<(i32, Command)>,
+}
+
+impl CommandRouter {
+    /// `/` is the prefix if none is given
+    pub(crate) fn new(mut prefixes: Vec<Prefix>, commands: Vec<(i32, Command)>) -> Self {
+        if prefixes.is_empty() {
+            prefixes.push(Prefix::Text("/"));
+        }
+        CommandRouter { prefixes, commands }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub(crate) fn find(&self, message: &Message, self_id: UserId) -> Option<Matched<'_>> {
+        let mut argv = split(message);
+        let name = match argv.first()? {
+            Word::Mention(user_id)
+                if *user_id == self_id && self.prefixes.contains(&Prefix::Mention) =>
+            {
+                argv.remove(0);
+                let word = argv.first()?.as_text()?;
+                // the text prefix is optional after a mention
+                self.strip_prefix(word).unwrap_or(word).to_owned()
+            }
+            Word::Text(word) => self.strip_prefix(word)?.to_owned(),
+            Word::Mention(_) => return None,
+        };
+        if name.is_empty() {
+            return None;
+        }
+        argv[0] = Word::Text(name);
+
+        let matches =
+            |command: &Command, word: &Word| word.as_text().is_some_and(|w| command.matches(w));
+        let found = self.commands.iter().find(|(_, c)| matches(c, &argv[0]));
+        let (priority, mut command) = match found {
+            Some((priority, command)) => (*priority, command),
+            None if argv[0].as_text() == Some(HELP) => {
+                return Some(Matched {
+                    priority: DEFAULT_PRIORITY,
+                    command: CommandMatch::Help(self.help(argv.get(1).and_then(Word::as_text))),
+                    argv,
+                    command_len: 1,
+                })
+            }
+            None => return None,
+        };
+        let mut command_len = 1;
+        while let Some(subcommand) = argv
+            .get(command_len)
+            .and_then(|word| command.subcommands.iter().find(|c| matches(c, word)))
+        {
+            command = subcommand;
+            command_len += 1;
+        }
+
+        let command = match &command.handler {
+            Some(handler) => CommandMatch::Handler(handler),
+            None => {
+                let mut listing = String::new();
+                let path = argv[..command_len - 1]
+                    .iter()
+                    .map(|word| format!("{} ", word))
+                    .collect::<String>();
+                command.help(&format!("{}{}", self.display_prefix(), path), &mut listing);
+                CommandMatch::Help(listing.trim_start().to_owned())
+            }
+        };
+        Some(Matched {
+            priority,
+            command,
+            argv,
+            command_len,
+        })
+    }
+
+    fn strip_prefix<'a>(&self, word: &'a str) -> Option<&'a str> {
+        self.prefixes.iter().find_map(|prefix| match prefix {
+            Prefix::Text(prefix) => word.strip_prefix(prefix),
+            Prefix::Mention => None,
+        })
+    }
+
+    /// The prefix shown in the help listing
+    fn display_prefix(&self) -> &'static str {
+        self.prefixes
+            .iter()
+            .find_map(|prefix| match prefix {
+                Prefix::Text(prefix) => Some(*prefix),
+                Prefix::Mention => None,
+            })
+            .unwrap_or("")
+    }
+
+    /// Every command, or the one named `name` with its sub-commands
+    fn help(&self, name: Option<&str>) -> String {
+        let prefix = self.display_prefix();
+        let mut listing = String::new();
+        match name.and_then(|name| self.commands.iter().find(|(_, c)| c.matches(name))) {
+            Some((_, command)) => command.help(prefix, &mut listing),
+            None => {
+                listing.push_str("Commands:");
+                for (_, command) in self.commands.iter() {
+                    command.help(prefix, &mut listing);
+                }
+                let _ = write!(listing, "\n{}{} - List commands", prefix, HELP);
+            }
+        }
+        listing.trim_start().to_owned()
+    }
+}
+
+/// A word of a command line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Word {
+    Text(String),
+    /// An @mention of a user, typed text never reads as one
+    Mention(UserId),
+}
+
+impl Word {
+    /// `None` for mentions
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Word::Text(text) => Some(text),
+            Word::Mention(_) => None,
+        }
+    }
+}
+
+/// Mentions are shown as `@` and the user id
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Word::Text(text) => f.write_str(text),
+            Word::Mention(user_id) => write!(f, "@{}", user_id),
+        }
+    }
+}
+
+/// Split a message into words like a shell does
+///
+/// Words are separated by whitespace, `'...'` quotes literally, `"..."` and `\` quote
+/// with backslash escapes. Each @mention of a user is a word of its own, even inside
+/// quotes, other non-text segments separate words.
+pub fn split(message: &Message) -> Vec<Word> {
+    let mut argv = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for segment in message {
+        let text = match segment {
+            MessageSegment::Text { text } => text,
+            segment => {
+                // `@all` is no mention of a user
+                let mention = match segment {
+                    MessageSegment::At { qq } => qq.parse().ok(),
+                    _ => None,
+                };
+                match mention {
+                    Some(user_id) => {
+                        argv.extend(word.take().map(Word::Text));
+                        argv.push(Word::Mention(user_id));
+                    }
+                    None if quote.is_none() => argv.extend(word.take().map(Word::Text)),
+                    None => (),
+                }
+                continue;
+            }
+        };
+
+        for c in text.chars() {
+            match (quote, c) {
+                _ if escaped => {
+                    escaped = false;
+                    word.get_or_insert_with(String::new).push(c);
+                }
+                (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+                (Some('\''), _) => word.get_or_insert_with(String::new).push(c),
+                (_, '\\') => escaped = true,
+                (Some(_), _) => word.get_or_insert_with(String::new).push(c),
+                (None, '\'') | (None, '"') => {
+                    quote = Some(c);
+                    // `''` is an empty word
+                    word.get_or_insert_with(String::new);
+                }
+                (None, c) if c.is_whitespace() => argv.extend(word.take().map(Word::Text)),
+                (None, _) => word.get_or_insert_with(String::new).push(c),
+            }
+        }
+    }
+    // an unterminated quote runs to the end
+    argv.extend(word.take().map(Word::Text));
+    argv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::args::ArgParser;
+
+    fn router() -> CommandRouter {
+        let ping = Command::new("ping").handler(|_, _| Box::pin(async { Propagation::Continue }));
+        CommandRouter::new(vec![Prefix::Mention], vec![(DEFAULT_PRIORITY, ping)])
+    }
+
+    fn text(text: &str) -> Word {
+        Word::Text(text.to_owned())
+    }
+
+    #[test]
+    fn mention_prefix() {
+        let router = router();
+        let message = Message::new().at(UserId(10000)).text(" ping");
+        let matched = router.find(&message, UserId(10000)).unwrap();
+        assert_eq!(matched.argv, [text("ping")]);
+    }
+
+    #[test]
+    fn typed_mention_is_text() {
+        let message = Message::from("[CQ:at,qq=10000] ping");
+        assert_eq!(split(&message), [text("[CQ:at,qq=10000]"), text("ping")]);
+        assert!(router().find(&message, UserId(10000)).is_none());
+    }
+
+    #[test]
+    fn text_is_unescaped() {
+        let message = Message::from("/echo a&b <x> '[y]'");
+        assert_eq!(
+            split(&message),
+            [text("/echo"), text("a&b"), text("<x>"), text("[y]")]
+        );
+    }
+
+    #[test]
+    fn mentions_are_words() {
+        let message = Message::new()
+            .text("/ban")
+            .at(UserId(10001))
+            .text("\"for ")
+            .at(UserId(10002))
+            .text("\"")
+            .at_all();
+        assert_eq!(
+            split(&message),
+            [
+                text("/ban"),
+                Word::Mention(UserId(10001)),
+                text("for "),
+                Word::Mention(UserId(10002)),
+            ]
+        );
+    }
+
+    #[test]
+    fn mention_argument() {
+        let message = Message::new()
+            .text("/ban ")
+            .at(UserId(10001))
+            .text(" [CQ:at,qq=10002]");
+        let argv = split(&message);
+        let mut args = ArgParser::new(&argv[1..], &[], &[]).unwrap();
+        assert_eq!(args.positional::<UserId>("user", None), Ok(UserId(10001)));
+        assert!(args.positional::<UserId>("user", None).is_err());
+    }
+}
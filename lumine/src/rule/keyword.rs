@@ -4,7 +4,7 @@ use crate::bot::MessageHandlerType;
 
 pub(crate) struct KeywordRuleBuilder {
     keywords: Vec<&'static str>,
-    handlers: Vec<MessageHandlerType>,
+    handlers: Vec<(i32, MessageHandlerType)>,
 }
 
 impl KeywordRuleBuilder {
@@ -14,9 +14,14 @@ impl KeywordRuleBuilder {
             handlers: Vec::new(),
         }
     }
-    pub(crate) fn insert(&mut self, keyword: &'static str, handler: MessageHandlerType) {
+    pub(crate) fn insert(
+        &mut self,
+        keyword: &'static str,
+        priority: i32,
+        handler: MessageHandlerType,
+    ) {
         self.keywords.push(keyword);
-        self.handlers.push(handler);
+        self.handlers.push((priority, handler));
     }
     pub(crate) fn build(self) -> KeywordRule {
         KeywordRule {
@@ -28,11 +33,12 @@ impl KeywordRuleBuilder {
 
 pub(crate) struct KeywordRule {
     matcher: AhoCorasick,
-    handlers: Vec<MessageHandlerType>,
+    handlers: Vec<(i32, MessageHandlerType)>,
 }
 
 impl KeywordRule {
-    pub(crate) fn find(&self, keyword: &str) -> Option<&MessageHandlerType> {
+    /// The handler of the first keyword in `keyword`, with its priority
+    pub(crate) fn find(&self, keyword: &str) -> Option<&(i32, MessageHandlerType)> {
         //TODO: match multi pattern
         self.matcher.find(keyword).map(|r| &self.handlers[r.pattern()])
     }
@@ -1 +1,4 @@
-pub mod keyword;
\ No newline at end of file
+pub mod args;
+pub mod command;
+pub mod keyword;
+pub mod text;
\ No newline at end of file
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
+use regex::{Regex, RegexSet};
+
+use crate::bot::MessageHandlerType;
+
+/// How the plain text of a message is matched, surrounding whitespace is trimmed first
+#[derive(Clone, Debug)]
+pub struct TextMatcher {
+    kind: MatchKind,
+    pattern: &'static str,
+    ignore_case: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MatchKind {
+    StartsWith,
+    EndsWith,
+    FullMatch,
+    Regex,
+}
+
+impl TextMatcher {
+    pub fn starts_with(prefix: &'static str) -> Self {
+        Self::new(MatchKind::StartsWith, prefix)
+    }
+
+    pub fn ends_with(suffix: &'static str) -> Self {
+        Self::new(MatchKind::EndsWith, suffix)
+    }
+
+    pub fn full_match(text: &'static str) -> Self {
+        Self::new(MatchKind::FullMatch, text)
+    }
+
+    /// Matches anywhere unless anchored, the captures are passed to the handler
+    ///
+    /// [`BotBuilder::build`](crate::bot::BotBuilder::build) panics on an invalid pattern.
+    pub fn regex(pattern: &'static str) -> Self {
+        Self::new(MatchKind::Regex, pattern)
+    }
+
+    /// Compare case-insensitively
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
+    fn new(kind: MatchKind, pattern: &'static str) -> Self {
+        TextMatcher {
+            kind,
+            pattern,
+            ignore_case: false,
+        }
+    }
+
+    /// The pattern as compared, reversed for suffixes so they can be matched as prefixes
+    fn normalized(&self) -> String {
+        let pattern = match self.ignore_case {
+            true => self.pattern.to_lowercase(),
+            false => self.pattern.to_owned(),
+        };
+        match self.kind {
+            MatchKind::EndsWith => pattern.chars().rev().collect(),
+            _ => pattern,
+        }
+    }
+}
+
+/// Groups captured by a regex rule, group 0 is the whole match
+#[derive(Clone, Debug, Default)]
+pub struct Captures {
+    groups: Vec<Option<String>>,
+    names: HashMap<String, usize>,
+}
+
+impl Captures {
+    fn new(regex: &Regex, text: &str) -> Self {
+        let groups = match regex.captures(text) {
+            Some(captures) => captures
+                .iter()
+                .map(|group| group.map(|group| group.as_str().to_owned()))
+                .collect(),
+            None => Vec::new(),
+        };
+        let names = regex
+            .capture_names()
+            .enumerate()
+            .filter_map(|(i, name)| name.map(|name| (name.to_owned(), i)))
+            .collect();
+        Captures { groups, names }
+    }
+
+    pub fn get(&self, i: usize) -> Option<&str> {
+        self.groups.get(i)?.as_deref()
+    }
+
+    pub fn name(&self, name: &str) -> Option<&str> {
+        self.get(*self.names.get(name)?)
+    }
+
+    /// Number of groups, including group 0
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+/// Prefixes matched at once, or suffixes matched as prefixes of the reversed text
+struct Anchored {
+    matcher: AhoCorasick,
+    handlers: Vec<usize>,
+    reversed: bool,
+    ignore_case: bool,
+}
+
+impl Anchored {
+    fn find(&self, text: &str, lowercase: &str, matched: &mut Vec<usize>) {
+        let text = if self.ignore_case { lowercase } else { text };
+        let reversed: String;
+        let text = if self.reversed {
+            reversed = text.chars().rev().collect();
+            &reversed
+        } else {
+            text
+        };
+        matched.extend(
+            self.matcher
+                .find_overlapping_iter(text)
+                .map(|m| self.handlers[m.pattern()]),
+        );
+    }
+}
+
+pub(crate) struct TextRuleBuilder {
+    rules: Vec<(TextMatcher, i32, MessageHandlerType)>,
+}
+
+impl TextRuleBuilder {
+    pub(crate) fn new() -> Self {
+        TextRuleBuilder { rules: Vec::new() }
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        matcher: TextMatcher,
+        priority: i32,
+        handler: MessageHandlerType,
+    ) {
+        self.rules.push((matcher, priority, handler));
+    }
+
+    pub(crate) fn build(self) -> TextRule {
+        let mut anchored = Vec::new();
+        for &(kind, ignore_case) in [
+            (MatchKind::StartsWith, false),
+            (MatchKind::StartsWith, true),
+            (MatchKind::EndsWith, false),
+            (MatchKind::EndsWith, true),
+        ]
+        .iter()
+        {
+            let (patterns, handlers): (Vec<_>, Vec<_>) = self
+                .rules
+                .iter()
+                .enumerate()
+                .filter(|(_, (m, _, _))| m.kind == kind && m.ignore_case == ignore_case)
+                .map(|(i, (m, _, _))| (m.normalized(), i))
+                .unzip();
+            if patterns.is_empty() {
+                continue;
+            }
+            anchored.push(Anchored {
+                matcher: AhoCorasickBuilder::new()
+                    .anchored(true)
+                    .dfa(true)
+                    .build(&patterns),
+                handlers,
+                reversed: kind == MatchKind::EndsWith,
+                ignore_case,
+            });
+        }
+
+        let mut full_match = HashMap::<_, Vec<_>>::new();
+        let mut regexes = Vec::new();
+        for (i, (matcher, _, _)) in self.rules.iter().enumerate() {
+            match matcher.kind {
+                MatchKind::FullMatch => full_match
+                    .entry((matcher.normalized(), matcher.ignore_case))
+                    .or_default()
+                    .push(i),
+                MatchKind::Regex => {
+                    let pattern = match matcher.ignore_case {
+                        true => format!("(?i){}", matcher.pattern),
+                        false => matcher.pattern.to_owned(),
+                    };
+                    let regex = Regex::new(&pattern)
+                        .unwrap_or_else(|e| panic!("Invalid regex rule {:?}: {}", pattern, e));
+                    regexes.push((regex, i));
+                }
+                _ => (),
+            }
+        }
+        let regex_set = RegexSet::new(regexes.iter().map(|(regex, _)| regex.as_str()))
+            .expect("regex rules compiled separately");
+
+        TextRule {
+            handlers: self.rules.into_iter().map(|(_, p, f)| (p, f)).collect(),
+            anchored,
+            full_match,
+            regex_set,
+            regexes,
+        }
+    }
+}
+
+pub(crate) struct TextRule {
+    handlers: Vec<(i32, MessageHandlerType)>,
+    anchored: Vec<Anchored>,
+    /// Handlers by normalized text and whether the case is ignored
+    full_match: HashMap<(String, bool), Vec<usize>>,
+    regex_set: RegexSet,
+    regexes: Vec<(Regex, usize)>,
+}
+
+impl TextRule {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    /// Every handler whose rule matches `text`, in registration order
+    pub(crate) fn find(&self, text: &str) -> Vec<(&(i32, MessageHandlerType), Captures)> {
+        let text = text.trim();
+        let lowercase = text.to_lowercase();
+
+        let mut matched = Vec::new();
+        for anchored in self.anchored.iter() {
+            anchored.find(text, &lowercase, &mut matched);
+        }
+        for key in [(text.to_owned(), false), (lowercase, true)].iter() {
+            if let Some(handlers) = self.full_match.get(key) {
+                matched.extend(handlers);
+            }
+        }
+        let mut matched: Vec<_> = matched
+            .into_iter()
+            .map(|i| (i, Captures::default()))
+            .collect();
+        for i in self.regex_set.matches(text).into_iter() {
+            let (regex, handler) = &self.regexes[i];
+            matched.push((*handler, Captures::new(regex, text)));
+        }
+
+        matched.sort_by_key(|(i, _)| *i);
+        matched
+            .into_iter()
+            .map(|(i, captures)| (&self.handlers[i], captures))
+            .collect()
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use futures::{
+    future::{self, Either},
+    Future,
+};
+use tokio::sync::watch;
+
+/// Stops a running [`Bot`](crate::Bot): no more connections are accepted, handlers in
+/// flight get until the shutdown timeout to finish, then connections are closed
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownHandle {
+    pub(crate) fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        ShutdownHandle {
+            sender: Arc::new(sender),
+        }
+    }
+
+    pub fn shutdown(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    pub(crate) fn signal(&self) -> ShutdownSignal {
+        ShutdownSignal(self.sender.subscribe())
+    }
+}
+
+pub(crate) struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    pub(crate) async fn wait(&mut self) {
+        // the sender lives in the bot, it is never dropped while the bot runs
+        while !*self.0.borrow_and_update() {
+            if self.0.changed().await.is_err() {
+                future::pending::<()>().await;
+            }
+        }
+    }
+
+    /// Output of `f`, or `None` if shutdown comes first
+    pub(crate) async fn until<F: Future>(&mut self, f: F) -> Option<F::Output> {
+        match future::select(Box::pin(f), Box::pin(self.wait())).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+}
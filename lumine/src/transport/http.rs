@@ -0,0 +1,52 @@
+use futures::future::BoxFuture;
+use hyper::{
+    client::HttpConnector,
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Body, Client, Request,
+};
+use serde_json::Value;
+
+use crate::{
+    error::{Error, Result},
+    protocol::api::ApiResponse,
+    transport::ActionSink,
+};
+
+/// `POST {url}/{action}` to the HTTP API of a OneBot implementation, plain HTTP only
+pub struct HttpActionSink {
+    client: Client<HttpConnector>,
+    url: String,
+    access_token: String,
+}
+
+impl HttpActionSink {
+    pub fn new(url: impl Into<String>, access_token: impl Into<String>) -> Self {
+        HttpActionSink {
+            client: Client::new(),
+            url: url.into().trim_end_matches('/').to_owned(),
+            access_token: access_token.into(),
+        }
+    }
+}
+
+impl ActionSink for HttpActionSink {
+    fn send_action(&self, action: String, params: Value) -> BoxFuture<'_, Result<ApiResponse>> {
+        Box::pin(async move {
+            let mut request = Request::post(format!("{}/{}", self.url, action))
+                .header(CONTENT_TYPE, "application/json");
+            if !self.access_token.is_empty() {
+                request = request.header(AUTHORIZATION, format!("Bearer {}", self.access_token));
+            }
+            let request = request
+                .body(Body::from(params.to_string()))
+                .map_err(|_| Error::InvalidUrl(self.url.clone()))?;
+
+            let response = self.client.request(request).await?;
+            if !response.status().is_success() {
+                return Err(Error::HttpStatus(response.status().as_u16()));
+            }
+            let body = hyper::body::to_bytes(response.into_body()).await?;
+            Ok(serde_json::from_slice(&body)?)
+        })
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::Mutex;
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::{
+    error::Result,
+    protocol::api::{ApiResponse, Status},
+    transport::{ActionSink, EventSource, Incoming},
+};
+
+type Responder = Box<dyn Fn(&str, &Value) -> Value + Send + Sync>;
+
+/// Events sent through the sender come out of the source, which closes with the sender
+pub fn channel() -> (UnboundedSender<Incoming>, MemoryEventSource) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (tx, MemoryEventSource(rx))
+}
+
+pub struct MemoryEventSource(UnboundedReceiver<Incoming>);
+
+impl EventSource for MemoryEventSource {
+    fn next_event(&mut self) -> BoxFuture<'_, Option<Result<Incoming>>> {
+        Box::pin(async move { self.0.recv().await.map(Ok) })
+    }
+}
+
+/// Records every action and answers it successfully with the data of the responder
+pub struct MemoryActionSink {
+    actions: Mutex<Vec<(String, Value)>>,
+    responder: Responder,
+}
+
+impl MemoryActionSink {
+    /// Answer every action with `null` data
+    pub fn new() -> Self {
+        Self::with_responder(|_, _| Value::Null)
+    }
+
+    pub fn with_responder(f: impl Fn(&str, &Value) -> Value + Send + Sync + 'static) -> Self {
+        MemoryActionSink {
+            actions: Mutex::new(Vec::new()),
+            responder: Box::new(f),
+        }
+    }
+
+    /// Actions and their params, in the order they were sent
+    pub fn actions(&self) -> Vec<(String, Value)> {
+        self.actions.lock().unwrap().clone()
+    }
+}
+
+impl Default for MemoryActionSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActionSink for MemoryActionSink {
+    fn send_action(&self, action: String, params: Value) -> BoxFuture<'_, Result<ApiResponse>> {
+        let data = (self.responder)(&action, &params);
+        self.actions.lock().unwrap().push((action, params));
+        Box::pin(async move {
+            Ok(ApiResponse {
+                status: Status::Ok,
+                retcode: 0,
+                data,
+                msg: None,
+                wording: None,
+                echo: None,
+            })
+        })
+    }
+}
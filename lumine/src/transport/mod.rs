@@ -0,0 +1,163 @@
+use futures::future::BoxFuture;
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+#[cfg(feature = "v12")]
+use crate::protocol::v12;
+use crate::{
+    error::Result,
+    protocol::{
+        api::{ApiResponse, QuickOperation},
+        event::Event,
+        id::UserId,
+    },
+};
+
+pub mod http;
+pub mod memory;
+pub(crate) mod webhook;
+pub mod websocket;
+
+/// Event of either OneBot version, v12 ones are received with the `v12` feature
+#[derive(Clone, Debug)]
+pub enum AnyEvent {
+    V11(Event),
+    #[cfg(feature = "v12")]
+    V12(v12::Event),
+}
+
+impl AnyEvent {
+    /// Whether a frame is an event rather than an API response
+    pub(crate) fn is_event(frame: &Value) -> bool {
+        frame.get("post_type").is_some()
+            || cfg!(feature = "v12") && frame.get("detail_type").is_some()
+    }
+
+    /// Parse an event frame, by its `post_type` in v11 or `detail_type` in v12
+    pub(crate) fn from_value(frame: Value) -> serde_json::Result<Self> {
+        #[cfg(feature = "v12")]
+        {
+            if frame.get("post_type").is_none() {
+                return serde_json::from_value(frame).map(AnyEvent::V12);
+            }
+        }
+        serde_json::from_value(frame).map(AnyEvent::V11)
+    }
+
+    /// The account the event belongs to, v12 ones only if their `self.user_id` is numeric
+    pub fn self_id(&self) -> Option<UserId> {
+        match self {
+            AnyEvent::V11(event) => Some(event.info().self_id),
+            #[cfg(feature = "v12")]
+            AnyEvent::V12(event) => event.info().bot_self.as_ref()?.user_id.parse().ok(),
+        }
+    }
+}
+
+impl From<Event> for AnyEvent {
+    fn from(event: Event) -> Self {
+        AnyEvent::V11(event)
+    }
+}
+
+#[cfg(feature = "v12")]
+impl From<v12::Event> for AnyEvent {
+    fn from(event: v12::Event) -> Self {
+        AnyEvent::V12(event)
+    }
+}
+
+/// Event received by a transport
+pub struct Incoming {
+    pub event: AnyEvent,
+    /// Receives the quick operation once every handler is done, for transports that
+    /// answer each event such as HTTP POST reports
+    pub quick_operation: Option<oneshot::Sender<QuickOperation>>,
+}
+
+impl From<AnyEvent> for Incoming {
+    fn from(event: AnyEvent) -> Self {
+        Incoming {
+            event,
+            quick_operation: None,
+        }
+    }
+}
+
+impl From<Event> for Incoming {
+    fn from(event: Event) -> Self {
+        AnyEvent::from(event).into()
+    }
+}
+
+#[cfg(feature = "v12")]
+impl From<v12::Event> for Incoming {
+    fn from(event: v12::Event) -> Self {
+        AnyEvent::from(event).into()
+    }
+}
+
+/// Where events come from
+pub trait EventSource: Send {
+    /// Wait for the next event, `None` once the source is closed
+    ///
+    /// Errors don't close the source, unless `None` follows.
+    fn next_event(&mut self) -> BoxFuture<'_, Option<Result<Incoming>>>;
+
+    /// Tell the other side goodbye on shutdown, the source is not read afterwards
+    fn close(&mut self) -> BoxFuture<'_, ()> {
+        Box::pin(async {})
+    }
+}
+
+/// Where actions go to
+pub trait ActionSink: Send + Sync {
+    /// Send an action and wait for its response, however long it takes
+    fn send_action(&self, action: String, params: Value) -> BoxFuture<'_, Result<ApiResponse>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn v11_event_is_parsed_as_v11() {
+        let frame = json!({
+            "time": 1515204254,
+            "self_id": 10001000,
+            "post_type": "meta_event",
+            "meta_event_type": "lifecycle",
+            "sub_type": "connect"
+        });
+        assert!(AnyEvent::is_event(&frame));
+        let event = AnyEvent::from_value(frame).unwrap();
+        assert!(matches!(event, AnyEvent::V11(_)));
+        assert_eq!(event.self_id(), Some(UserId(10001000)));
+    }
+
+    #[cfg(feature = "v12")]
+    #[test]
+    fn v12_event_is_parsed_as_v12() {
+        let frame = json!({
+            "id": "b6e65187-5ac0-489c-b431-53078e9d2bbb",
+            "self": { "platform": "qq", "user_id": "123234" },
+            "time": 1632847927.599013,
+            "type": "notice",
+            "detail_type": "friend_increase",
+            "sub_type": "",
+            "user_id": "123456788"
+        });
+        assert!(AnyEvent::is_event(&frame));
+        let event = AnyEvent::from_value(frame).unwrap();
+        assert!(matches!(event, AnyEvent::V12(_)));
+        assert_eq!(event.self_id(), Some(UserId(123234)));
+    }
+
+    #[test]
+    fn response_is_not_an_event() {
+        let frame = json!({ "status": "ok", "retcode": 0, "data": null, "echo": 1 });
+        assert!(!AnyEvent::is_event(&frame));
+    }
+}
@@ -0,0 +1,184 @@
+use std::{convert::Infallible, sync::Arc};
+
+use futures::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use hyper::{
+    header::{HeaderValue, CONTENT_TYPE},
+    server::conn::Http,
+    service::service_fn,
+    Body, Method, Request, Response, StatusCode,
+};
+use log::debug;
+use serde_json::Value;
+use sha1::Sha1;
+use tokio::{
+    net::TcpStream,
+    sync::{
+        mpsc::{UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+};
+
+use crate::{
+    error::{Error, Result},
+    transport::{AnyEvent, EventSource, Incoming},
+    Bot,
+};
+
+/// Events of HTTP POST reports, each report waits for its quick operation
+pub(crate) struct WebhookEventSource(pub(crate) UnboundedReceiver<Result<Incoming>>);
+
+impl EventSource for WebhookEventSource {
+    fn next_event(&mut self) -> BoxFuture<'_, Option<Result<Incoming>>> {
+        Box::pin(self.0.recv())
+    }
+}
+
+fn response(status: StatusCode, body: impl Into<Body>) -> Response<Body> {
+    let mut response = Response::new(body.into());
+    *response.status_mut() = status;
+    response
+}
+
+/// `X-Signature` is `sha1=` followed by the hex HMAC-SHA1 of the body, keyed by the secret
+fn verify_signature(secret: &str, body: &[u8], signature: Option<&HeaderValue>) -> bool {
+    let signature = signature
+        .and_then(|s| s.as_bytes().strip_prefix(b"sha1="))
+        .and_then(|s| hex::decode(s).ok());
+    let signature = match signature {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC takes any key");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+async fn handle_request(
+    bot: Arc<Bot>,
+    events: UnboundedSender<Result<Incoming>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    if request.uri().path() != bot.entry_point {
+        return Ok(response(StatusCode::NOT_FOUND, "No such entry point"));
+    }
+    if request.method() != Method::POST {
+        return Ok(response(StatusCode::METHOD_NOT_ALLOWED, Body::empty()));
+    }
+
+    let signature = request.headers().get("X-Signature").cloned();
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(e) => {
+            let _ = events.send(Err(e.into()));
+            return Ok(response(StatusCode::BAD_REQUEST, Body::empty()));
+        }
+    };
+    debug!("Get http data: {:?}", body);
+
+    if !bot.secret.is_empty() && !verify_signature(bot.secret, &body, signature.as_ref()) {
+        return Ok(response(StatusCode::FORBIDDEN, "Signature incorrect"));
+    }
+
+    let event = match serde_json::from_slice(&body).and_then(AnyEvent::from_value) {
+        Ok(event) => event,
+        Err(error) => {
+            let frame = String::from_utf8_lossy(&body).into_owned();
+            let _ = events.send(Err(Error::MalformedFrame { frame, error }));
+            return Ok(response(StatusCode::NO_CONTENT, Body::empty()));
+        }
+    };
+
+    // handlers run before responding, so that they can fill in the quick operation
+    let (tx, rx) = oneshot::channel();
+    let incoming = Incoming {
+        event,
+        quick_operation: Some(tx),
+    };
+    if events.send(Ok(incoming)).is_err() {
+        return Ok(response(StatusCode::SERVICE_UNAVAILABLE, Body::empty()));
+    }
+    let operation = serde_json::to_value(rx.await.unwrap_or_default()).unwrap();
+
+    match operation {
+        Value::Object(ref o) if o.is_empty() => Ok(response(StatusCode::NO_CONTENT, Body::empty())),
+        operation => {
+            let mut response = response(StatusCode::OK, operation.to_string());
+            response
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            Ok(response)
+        }
+    }
+}
+
+pub(crate) async fn handle_connection(
+    stream: TcpStream,
+    bot: Arc<Bot>,
+    events: UnboundedSender<Result<Incoming>>,
+) -> Result<()> {
+    let service = service_fn(move |request| handle_request(bot.clone(), events.clone(), request));
+    Http::new()
+        .http1_only(true)
+        .serve_connection(stream, service)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // HMAC-SHA1 of the body keyed by "key"
+    const BODY: &[u8] = b"The quick brown fox jumps over the lazy dog";
+    const SIGNATURE: &str = "sha1=de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9";
+
+    fn verify(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+        let signature = signature.map(|s| HeaderValue::from_str(s).unwrap());
+        verify_signature(secret, body, signature.as_ref())
+    }
+
+    #[test]
+    fn signature_accepted() {
+        assert!(verify("key", BODY, Some(SIGNATURE)));
+        assert!(verify(
+            "key",
+            BODY,
+            Some(&SIGNATURE.replace("de7c", "DE7C"))
+        ));
+    }
+
+    #[test]
+    fn signature_mismatch_rejected() {
+        assert!(!verify("other", BODY, Some(SIGNATURE)));
+        assert!(!verify("key", b"tampered", Some(SIGNATURE)));
+    }
+
+    #[test]
+    fn signature_missing_rejected() {
+        assert!(!verify("key", BODY, None));
+    }
+
+    #[test]
+    fn signature_malformed_rejected() {
+        assert!(!verify(
+            "key",
+            BODY,
+            Some(SIGNATURE.trim_start_matches("sha1="))
+        ));
+        assert!(!verify(
+            "key",
+            BODY,
+            Some(&SIGNATURE.replace("sha1=", "sha256="))
+        ));
+        assert!(!verify(
+            "key",
+            BODY,
+            Some(&SIGNATURE.replace("sha1=", "SHA1="))
+        ));
+        assert!(!verify("key", BODY, Some("sha1=not-hex")));
+        assert!(!verify("key", BODY, Some("sha1=de7c")));
+        assert!(!verify("key", BODY, Some("")));
+    }
+}
@@ -0,0 +1,511 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+use futures::{
+    future::{self, BoxFuture, Either},
+    stream::{SplitStream, StreamExt},
+    SinkExt,
+};
+use log::{debug, info, warn};
+use serde_json::{from_slice, from_value, json, Value};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{
+        mpsc::{self, UnboundedSender},
+        oneshot,
+    },
+    time,
+};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+use crate::{
+    client::ApiClient,
+    error::{Error, Result},
+    handler,
+    protocol::{
+        api::ApiResponse,
+        handshake::{ClientInfo, ClientRole},
+        id::UserId,
+    },
+    transport::{ActionSink, AnyEvent, EventSource, Incoming},
+    Bot,
+};
+
+/// Calls waiting for their response, keyed by `echo`
+#[derive(Default)]
+struct PendingCalls {
+    next_echo: AtomicUsize,
+    calls: Mutex<HashMap<usize, oneshot::Sender<ApiResponse>>>,
+}
+
+impl PendingCalls {
+    fn register(&self) -> (usize, oneshot::Receiver<ApiResponse>) {
+        let echo = self.next_echo.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.calls.lock().unwrap().insert(echo, tx);
+        (echo, rx)
+    }
+
+    fn remove(&self, echo: usize) {
+        self.calls.lock().unwrap().remove(&echo);
+    }
+
+    fn resolve(&self, response: ApiResponse) {
+        let tx = match response.echo {
+            Some(echo) => self.calls.lock().unwrap().remove(&echo),
+            None => None,
+        };
+        match tx {
+            Some(tx) => {
+                // the caller may have timed out already
+                let _ = tx.send(response);
+            }
+            None => warn!("Response without pending call: {:?}", response),
+        }
+    }
+
+    /// Drop every pending call, their callers get a "connection closed" error
+    fn clear(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+}
+
+/// Forgets the call when its caller stops waiting, e.g. on timeout
+struct PendingGuard<'a> {
+    pending: &'a PendingCalls,
+    echo: usize,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.remove(self.echo);
+    }
+}
+
+/// Sends actions as frames tagged by `echo`, responses come back through
+/// the [`WebSocketEventSource`] of the same connection
+pub struct WebSocketActionSink {
+    sender: UnboundedSender<Message>,
+    pending: Arc<PendingCalls>,
+}
+
+impl ActionSink for WebSocketActionSink {
+    fn send_action(&self, action: String, params: Value) -> BoxFuture<'_, Result<ApiResponse>> {
+        Box::pin(async move {
+            let (echo, rx) = self.pending.register();
+            let _guard = PendingGuard {
+                pending: &self.pending,
+                echo,
+            };
+
+            let frame = json!({ "action": action, "params": params, "echo": echo });
+            self.sender
+                .send(Message::text(frame.to_string()))
+                .map_err(|_| Error::ConnectionClosed)?;
+            rx.await.map_err(|_| Error::ConnectionClosed)
+        })
+    }
+}
+
+pub struct WebSocketEventSource<S> {
+    reader: SplitStream<WebSocketStream<S>>,
+    sender: UnboundedSender<Message>,
+    pending: Arc<PendingCalls>,
+    /// Resolved if the writer fails, the connection is over then
+    writer_error: Option<oneshot::Receiver<Error>>,
+    closed: bool,
+}
+
+impl<S> WebSocketEventSource<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Resolve API responses, return events
+    fn receive(&self, frame: &[u8]) -> Result<Option<AnyEvent>> {
+        let malformed = |error| Error::MalformedFrame {
+            frame: String::from_utf8_lossy(frame).into_owned(),
+            error,
+        };
+
+        let undetermined_message: Value = from_slice(frame).map_err(malformed)?;
+        if AnyEvent::is_event(&undetermined_message) {
+            let event = AnyEvent::from_value(undetermined_message).map_err(malformed)?;
+            Ok(Some(event))
+        } else {
+            let response = from_value::<ApiResponse>(undetermined_message).map_err(malformed)?;
+            self.pending.resolve(response);
+            Ok(None)
+        }
+    }
+
+    /// Next frame, or the failure of the writer
+    async fn next_frame(&mut self) -> Option<Result<Message>> {
+        let frame = match self.writer_error.as_mut() {
+            Some(writer_error) => match future::select(self.reader.next(), writer_error).await {
+                Either::Left((frame, _)) => frame,
+                Either::Right((error, _)) => {
+                    self.writer_error = None;
+                    // `Err` when the writer ends normally, only after this source is dropped
+                    return error.ok().map(Err);
+                }
+            },
+            None => self.reader.next().await,
+        };
+        frame.map(|frame| frame.map_err(Error::from))
+    }
+}
+
+impl<S> EventSource for WebSocketEventSource<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    fn next_event(&mut self) -> BoxFuture<'_, Option<Result<Incoming>>> {
+        Box::pin(async move {
+            while !self.closed {
+                let result = self.next_frame().await;
+                debug!("Get websocket data: {:?}", result);
+                let frame = match result {
+                    Some(Ok(Message::Text(text))) => self.receive(text.as_bytes()),
+                    Some(Ok(Message::Binary(data))) => Err(Error::BinaryFrame(data.len())),
+                    Some(Ok(Message::Ping(frame))) => {
+                        match self.sender.send(Message::Pong(frame)) {
+                            Ok(()) => continue,
+                            Err(_) => Err(Error::ConnectionClosed),
+                        }
+                    }
+                    Some(Ok(Message::Pong(_))) => continue,
+                    Some(Ok(Message::Close(frame))) => {
+                        info!("Remote disconnect: {:?}", frame);
+                        break;
+                    }
+                    Some(Err(error)) => {
+                        // the connection is unusable after a transport error
+                        self.closed = true;
+                        Err(error)
+                    }
+                    None => break,
+                };
+                match frame {
+                    Ok(Some(event)) => return Some(Ok(event.into())),
+                    Ok(None) => continue,
+                    Err(error) => return Some(Err(error)),
+                }
+            }
+            self.closed = true;
+            self.pending.clear();
+            None
+        })
+    }
+
+    fn close(&mut self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            if self.sender.send(Message::Close(None)).is_ok() {
+                // wait for the close frame of the other side
+                while let Some(Ok(_)) = self.reader.next().await {}
+            }
+            self.closed = true;
+            self.pending.clear();
+        })
+    }
+}
+
+/// Split a websocket into its event source and action sink, and spawn the writer
+pub fn split<S>(stream: WebSocketStream<S>) -> (WebSocketEventSource<S>, WebSocketActionSink)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut writer, reader) = stream.split();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (error_tx, error_rx) = oneshot::channel();
+    let pending = Arc::new(PendingCalls::default());
+
+    // ends once both halves are dropped, or on failure
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            debug!("Send websocket data: {:?}", msg);
+            if let Err(e) = writer.send(msg).await {
+                let _ = error_tx.send(e.into());
+                break;
+            }
+        }
+    });
+
+    let source = WebSocketEventSource {
+        reader,
+        sender: tx.clone(),
+        pending: pending.clone(),
+        writer_error: Some(error_rx),
+        closed: false,
+    };
+    let sink = WebSocketActionSink {
+        sender: tx,
+        pending,
+    };
+    (source, sink)
+}
+
+/// Sends the actions of an event connection through the API connection of the same account
+struct PairedActionSink {
+    bot: Weak<Bot>,
+    self_id: UserId,
+}
+
+impl ActionSink for PairedActionSink {
+    fn send_action(&self, action: String, params: Value) -> BoxFuture<'_, Result<ApiResponse>> {
+        Box::pin(async move {
+            let sink = self
+                .bot
+                .upgrade()
+                .and_then(|bot| {
+                    bot.api_connections
+                        .lock()
+                        .unwrap()
+                        .get(&self.self_id)
+                        .cloned()
+                })
+                .ok_or(Error::NoConnection)?;
+            sink.send_action(action, params).await
+        })
+    }
+}
+
+pub(crate) async fn handle_connection<S>(
+    stream: WebSocketStream<S>,
+    bot: Arc<Bot>,
+    client_info: ClientInfo,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut source, sink) = split(stream);
+    let self_id = client_info.self_id;
+    let client = ApiClient::new(Arc::new(sink)).timeout(bot.api_timeout);
+
+    match (client_info.role, self_id) {
+        (ClientRole::Event, Some(self_id)) => {
+            info!("Event connection of {}", self_id);
+            let sink = PairedActionSink {
+                bot: Arc::downgrade(&bot),
+                self_id,
+            };
+            handler::serve(bot.clone(), source, Some(Arc::new(sink)), Some(self_id)).await;
+            return Ok(());
+        }
+        (ClientRole::Api, Some(self_id)) => {
+            info!("API connection of {}", self_id);
+            let sink = client.sink().unwrap();
+            bot.api_connections
+                .lock()
+                .unwrap()
+                .insert(self_id, sink.clone());
+            *bot.client.lock().unwrap() = Some(client.clone());
+
+            // responses are resolved while reading
+            let mut shutdown = bot.api_shutdown.signal();
+            while let Some(Some(incoming)) = shutdown.until(source.next_event()).await {
+                match incoming {
+                    Ok(incoming) => warn!("Event on API connection: {:?}", incoming.event),
+                    Err(e) => handler::report_error(&bot, e),
+                }
+            }
+            if bot.api_shutdown.is_shutdown() {
+                let _ = time::timeout(bot.shutdown_timeout, source.close()).await;
+            }
+
+            let mut connections = bot.api_connections.lock().unwrap();
+            if matches!(connections.get(&self_id), Some(s) if Arc::ptr_eq(s, &sink)) {
+                connections.remove(&self_id);
+            }
+        }
+        (role, _) => {
+            if role != ClientRole::Universal {
+                warn!(
+                    "{:?} connection without X-Self-ID, treat it as universal",
+                    role
+                );
+            }
+
+            *bot.client.lock().unwrap() = Some(client.clone());
+            handler::serve(bot.clone(), source, client.sink(), self_id).await;
+        }
+    }
+
+    let mut current = bot.client.lock().unwrap();
+    if matches!(&*current, Some(c) if c.is_same_connection(&client)) {
+        current.take();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tokio::{
+        io::{duplex, DuplexStream},
+        runtime,
+    };
+
+    use tokio_tungstenite::{accept_async, client_async};
+
+    use super::*;
+    use crate::{bot::BotBuilder, transport::memory::MemoryActionSink};
+
+    type Remote = WebSocketStream<DuplexStream>;
+
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(f)
+    }
+
+    /// The bot side of a connection, and the implementation side driven by the test
+    async fn connect() -> (
+        WebSocketEventSource<DuplexStream>,
+        WebSocketActionSink,
+        Remote,
+    ) {
+        let (bot, remote) = duplex(4096);
+        let (bot, remote) =
+            future::join(accept_async(bot), client_async("ws://localhost/", remote)).await;
+        let (source, sink) = split(bot.unwrap());
+        (source, sink, remote.unwrap().0)
+    }
+
+    async fn next_call(remote: &mut Remote) -> Value {
+        let frame = remote.next().await.unwrap().unwrap();
+        serde_json::from_str(frame.to_text().unwrap()).unwrap()
+    }
+
+    async fn respond(remote: &mut Remote, call: &Value) {
+        let response = json!({
+            "status": "ok",
+            "retcode": 0,
+            "data": call["action"],
+            "echo": call["echo"],
+        });
+        remote
+            .send(Message::text(response.to_string()))
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn responses_out_of_order() {
+        block_on(async {
+            let (mut source, sink, mut remote) = connect().await;
+            let calls = future::join(
+                sink.send_action("first".to_owned(), json!({})),
+                sink.send_action("second".to_owned(), json!({})),
+            );
+            let remote = async {
+                let first = next_call(&mut remote).await;
+                let second = next_call(&mut remote).await;
+                respond(&mut remote, &second).await;
+                respond(&mut remote, &first).await;
+                remote.close(None).await.unwrap();
+            };
+            let ((first, second), _, end) = future::join3(calls, remote, source.next_event()).await;
+
+            assert_eq!(first.unwrap().data, "first");
+            assert_eq!(second.unwrap().data, "second");
+            assert!(end.is_none());
+            assert!(sink.pending.calls.lock().unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn bad_frames_are_errors() {
+        block_on(async {
+            let (mut source, _sink, mut remote) = connect().await;
+            let event = json!({
+                "time": 1515204254,
+                "self_id": 10000,
+                "post_type": "meta_event",
+                "meta_event_type": "lifecycle",
+                "sub_type": "connect"
+            });
+            let frames = vec![
+                Message::text("not json"),
+                Message::binary(vec![0; 3]),
+                Message::text(event.to_string()),
+            ];
+            for frame in frames {
+                remote.send(frame).await.unwrap();
+            }
+
+            let malformed = source.next_event().await.unwrap();
+            assert!(matches!(malformed, Err(Error::MalformedFrame { .. })));
+            let binary = source.next_event().await.unwrap();
+            assert!(matches!(binary, Err(Error::BinaryFrame(3))));
+            let incoming = source.next_event().await.unwrap().unwrap();
+            assert_eq!(incoming.event.self_id(), Some(UserId(10000)));
+        });
+    }
+
+    #[test]
+    fn timed_out_call_is_forgotten() {
+        block_on(async {
+            let (_source, sink, _remote) = connect().await;
+            let call = sink.send_action("slow".to_owned(), json!({}));
+            let timeout = time::timeout(Duration::from_millis(10), call).await;
+
+            assert!(timeout.is_err());
+            assert!(sink.pending.calls.lock().unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn connection_closed_mid_call() {
+        block_on(async {
+            let (mut source, sink, mut remote) = connect().await;
+            let call = sink.send_action("lost".to_owned(), json!({}));
+            let remote = async {
+                next_call(&mut remote).await;
+                remote.close(None).await.unwrap();
+            };
+            let (call, _, end) = future::join3(call, remote, source.next_event()).await;
+
+            assert!(matches!(call, Err(Error::ConnectionClosed)));
+            assert!(end.is_none());
+        });
+    }
+
+    #[test]
+    fn event_connection_uses_api_connection_of_its_account() {
+        block_on(async {
+            let bot = Arc::new(BotBuilder::new("", "").build());
+            let api = Arc::new(MemoryActionSink::new());
+            bot.api_connections
+                .lock()
+                .unwrap()
+                .insert(UserId(10000), api.clone());
+            let paired = |self_id| PairedActionSink {
+                bot: Arc::downgrade(&bot),
+                self_id,
+            };
+
+            let sent = paired(UserId(10000))
+                .send_action("first".to_owned(), json!({}))
+                .await;
+            assert!(sent.is_ok());
+            let other = paired(UserId(10001))
+                .send_action("second".to_owned(), json!({}))
+                .await;
+            assert!(matches!(other, Err(Error::NoConnection)));
+            assert_eq!(api.actions(), [("first".to_owned(), json!({}))]);
+
+            let sink = paired(UserId(10000));
+            drop(bot);
+            let stopped = sink.send_action("third".to_owned(), json!({}));
+            assert!(matches!(stopped.await, Err(Error::NoConnection)));
+        });
+    }
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{private_message, replies, serve, Log};
+use lumine::{
+    bot::BotBuilder,
+    handler::Propagation,
+    rule::command::{Command, Prefix},
+};
+
+/// A handler adding the command arguments to `log`
+fn echo(log: &Log) -> Command {
+    let log = log.clone();
+    Command::new("echo")
+        .alias("say")
+        .description("Repeat the words")
+        .handler(move |ctx, _| {
+            let log = log.clone();
+            Box::pin(async move {
+                let words: Vec<_> = ctx.args().iter().map(ToString::to_string).collect();
+                log.lock().unwrap().push(words.join("|"));
+                Propagation::Continue
+            })
+        })
+}
+
+#[test]
+fn names_aliases_and_prefixes() {
+    let log = Log::default();
+    let bot = BotBuilder::new("", "")
+        .command_prefix(Prefix::Text("/"))
+        .command_prefix(Prefix::Text("!"))
+        .on_command(echo(&log));
+    let events = vec![
+        private_message(1, "/echo a 'b c' \"d&e\""),
+        private_message(1, "!say x"),
+        private_message(1, "echo y"),
+        private_message(1, "/echoes z"),
+        private_message(1, "what is /echo"),
+    ];
+    serve(bot, events);
+
+    assert_eq!(*log.lock().unwrap(), ["a|b c|d&e", "x"]);
+}
+
+#[test]
+fn subcommands_and_help() {
+    let log = Log::default();
+    let memory = Command::new("memory")
+        .description("Manage memories")
+        .subcommand(echo(&log));
+    let bot = BotBuilder::new("", "").on_command(memory);
+    let events = vec![
+        private_message(1, "/memory echo 1"),
+        private_message(1, "/memory"),
+        private_message(1, "/help memory"),
+    ];
+    let sink = serve(bot, events);
+
+    assert_eq!(*log.lock().unwrap(), ["1"]);
+    let replies = replies(&sink);
+    assert_eq!(replies.len(), 2);
+    for reply in replies {
+        assert!(
+            reply.contains("/memory echo, say - Repeat the words"),
+            "{}",
+            reply
+        );
+    }
+}
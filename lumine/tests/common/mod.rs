@@ -0,0 +1,95 @@
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+
+use lumine::{
+    bot::BotBuilder,
+    context::MessageContext,
+    handler::Propagation,
+    protocol::event::{message::MessageEvent, Event},
+    transport::{
+        memory::{self, MemoryActionSink},
+        Incoming,
+    },
+    AsyncFnReturnType,
+};
+use serde_json::{json, Value};
+use tokio::runtime;
+
+pub type Log = Arc<Mutex<Vec<String>>>;
+
+pub fn private_message(user_id: i64, text: &str) -> Incoming {
+    private_segments(
+        user_id,
+        json!([{ "type": "text", "data": { "text": text } }]),
+        text,
+    )
+}
+
+/// A private message of `segments` in array form, `raw` is its CQ code
+pub fn private_segments(user_id: i64, segments: Value, raw: &str) -> Incoming {
+    let event: Event = serde_json::from_value(json!({
+        "time": 1515204254,
+        "self_id": 10000,
+        "post_type": "message",
+        "message_type": "private",
+        "sub_type": "friend",
+        "message_id": 1,
+        "user_id": user_id,
+        "message": segments,
+        "raw_message": raw,
+        "font": 0,
+        "sender": { "user_id": user_id, "nickname": "" }
+    }))
+    .unwrap();
+    event.into()
+}
+
+/// Serve `events` until every handler is done, the returned sink holds the actions sent
+pub fn serve(bot: BotBuilder, events: Vec<Incoming>) -> Arc<MemoryActionSink> {
+    let sink = Arc::new(MemoryActionSink::with_responder(
+        |_, _| json!({ "message_id": 1 }),
+    ));
+    let (tx, source) = memory::channel();
+    for event in events {
+        tx.send(event).unwrap();
+    }
+    drop(tx);
+    runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(Arc::new(bot.build()).serve_transport(source, sink.clone()));
+    sink
+}
+
+/// Text of the private messages sent to `sink`
+pub fn replies(sink: &MemoryActionSink) -> Vec<String> {
+    sink.actions()
+        .into_iter()
+        .map(|(action, params)| {
+            assert_eq!(action, "send_private_msg");
+            params["message"][0]["data"]["text"]
+                .as_str()
+                .unwrap()
+                .to_owned()
+        })
+        .collect()
+}
+
+/// A handler adding `entry` to `log`
+pub fn record(
+    log: &Log,
+    entry: &'static str,
+    propagation: Propagation,
+) -> impl Fn(MessageContext, MessageEvent) -> AsyncFnReturnType<Propagation> + Send + Sync + 'static
+{
+    let log = log.clone();
+    move |_, _| {
+        let log = log.clone();
+        Box::pin(async move {
+            log.lock().unwrap().push(entry.to_owned());
+            propagation
+        })
+    }
+}
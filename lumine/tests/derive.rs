@@ -0,0 +1,80 @@
+mod common;
+
+use common::{private_message, private_segments, replies, serve, Log};
+use lumine::{
+    bot::BotBuilder, handler::Propagation, protocol::id::UserId, rule::command::Command, Command,
+};
+use serde_json::json;
+
+/// Add two numbers
+#[derive(Command)]
+struct Add {
+    a: i64,
+    b: i64,
+}
+
+#[test]
+fn derived_args_and_usage() {
+    let log = Log::default();
+    let handler_log = log.clone();
+    let add = Command::with_args(move |_, args: Add| {
+        let log = handler_log.clone();
+        Box::pin(async move {
+            log.lock().unwrap().push((args.a + args.b).to_string());
+            Propagation::Continue
+        })
+    });
+    let bot = BotBuilder::new("", "").on_command(add);
+    let events = vec![
+        private_message(1, "/add 1 2"),
+        private_message(1, "/add 1 x"),
+        private_message(1, "/help"),
+        private_message(1, "add 3 4"),
+    ];
+    let sink = serve(bot, events);
+
+    assert_eq!(*log.lock().unwrap(), ["3"]);
+    let replies = replies(&sink);
+    assert_eq!(replies.len(), 2);
+    assert!(replies[0].ends_with("Usage: add <a> <b>"), "{}", replies[0]);
+    assert!(
+        replies[1].contains("/add <a> <b> - Add two numbers"),
+        "{}",
+        replies[1]
+    );
+}
+
+/// Ban a user
+#[derive(Command)]
+struct Ban {
+    user: UserId,
+    #[arg(long, default = "60")]
+    minutes: u32,
+}
+
+#[test]
+fn mention_as_user_id() {
+    let log = Log::default();
+    let handler_log = log.clone();
+    let ban = Command::with_args(move |_, args: Ban| {
+        let log = handler_log.clone();
+        Box::pin(async move {
+            let entry = format!("{} {}", args.user, args.minutes);
+            log.lock().unwrap().push(entry);
+            Propagation::Continue
+        })
+    });
+    let bot = BotBuilder::new("", "").on_command(ban);
+    let segments = json!([
+        { "type": "text", "data": { "text": "/ban " } },
+        { "type": "at", "data": { "qq": "10001" } },
+        { "type": "text", "data": { "text": " --minutes 5" } }
+    ]);
+    let events = vec![
+        private_segments(1, segments, "/ban [CQ:at,qq=10001] --minutes 5"),
+        private_message(1, "/ban 10002"),
+    ];
+    serve(bot, events);
+
+    assert_eq!(*log.lock().unwrap(), ["10001 5", "10002 60"]);
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{private_message, serve, Log};
+use lumine::{
+    bot::BotBuilder,
+    handler::{DispatchPolicy, Propagation},
+};
+use tokio::time;
+
+#[test]
+fn per_conversation_keeps_order() {
+    let log = Log::default();
+    let handler_log = log.clone();
+    let bot = BotBuilder::new("", "")
+        .dispatch_policy(DispatchPolicy::PerConversation)
+        .on_message(move |_, e| {
+            let log = handler_log.clone();
+            Box::pin(async move {
+                let text = e.message().plain_text();
+                if text == "first" {
+                    time::sleep(Duration::from_millis(50)).await;
+                }
+                log.lock().unwrap().push(text);
+                Propagation::Continue
+            })
+        });
+    let events = vec![
+        private_message(1, "first"),
+        private_message(1, "second"),
+        private_message(2, "other"),
+    ];
+    serve(bot, events);
+
+    // another conversation does not wait for the first one
+    assert_eq!(*log.lock().unwrap(), ["other", "first", "second"]);
+}
@@ -0,0 +1,16 @@
+mod common;
+
+use common::{private_message, record, serve, Log};
+use lumine::{bot::BotBuilder, handler::Propagation};
+
+#[test]
+fn priority_and_stop() {
+    let log = Log::default();
+    let bot = BotBuilder::new("", "")
+        .on_message_with_priority(-1, record(&log, "low", Propagation::Continue))
+        .on_message(record(&log, "default", Propagation::Stop))
+        .on_message_with_priority(1, record(&log, "high", Propagation::Continue));
+    serve(bot, vec![private_message(1, "hi")]);
+
+    assert_eq!(*log.lock().unwrap(), ["high", "default"]);
+}
@@ -0,0 +1,25 @@
+mod common;
+
+use common::{private_message, record, serve, Log};
+use lumine::{bot::BotBuilder, handler::Propagation};
+
+#[test]
+fn text_rules() {
+    let log = Log::default();
+    let regex_log = log.clone();
+    let bot = BotBuilder::new("", "")
+        .on_starts_with("hello", record(&log, "starts", Propagation::Continue))
+        .on_regex(r"(?P<n>\d+) apples", move |ctx, _| {
+            let log = regex_log.clone();
+            Box::pin(async move {
+                let n = ctx.captures.name("n").unwrap_or_default().to_owned();
+                log.lock().unwrap().push(n);
+                Propagation::Continue
+            })
+        })
+        .on_ends_with("pears", record(&log, "ends", Propagation::Continue))
+        .on_full_match("hello", record(&log, "full", Propagation::Continue));
+    serve(bot, vec![private_message(1, "  hello, 3 apples ")]);
+
+    assert_eq!(*log.lock().unwrap(), ["starts", "3"]);
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "v12")]
+
+use std::sync::Arc;
+
+use lumine::{
+    bot::BotBuilder,
+    handler::Propagation,
+    protocol::v12::{self, event::MessageEvent, SendMessage},
+    transport::memory::{self, MemoryActionSink},
+};
+use serde_json::json;
+use tokio::runtime;
+
+#[test]
+fn message_event_reaches_v12_handlers() {
+    let bot = BotBuilder::new("", "")
+        .on_v12_event(|ctx, e| {
+            Box::pin(async move {
+                if let v12::Event::Message { event, .. } = e {
+                    if let MessageEvent::Private { user_id, .. } = &event {
+                        let reply = SendMessage::private(user_id.clone(), event.message().clone());
+                        ctx.action(reply).await.unwrap();
+                    }
+                }
+                Propagation::Continue
+            })
+        })
+        .build();
+    let sink = Arc::new(MemoryActionSink::with_responder(
+        |_, _| json!({ "message_id": "2", "time": 1632847927.0 }),
+    ));
+    let event: v12::Event = serde_json::from_value(json!({
+        "id": "b6e65187-5ac0-489c-b431-53078e9d2bbb",
+        "self": { "platform": "qq", "user_id": "123234" },
+        "time": 1632847927.599013,
+        "type": "message",
+        "detail_type": "private",
+        "sub_type": "",
+        "message_id": "6283",
+        "message": [{ "type": "text", "data": { "text": "OneBot is not a bot" } }],
+        "alt_message": "OneBot is not a bot",
+        "user_id": "123456788"
+    }))
+    .unwrap();
+
+    let (tx, source) = memory::channel();
+    tx.send(event.into()).unwrap();
+    drop(tx);
+    runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(Arc::new(bot).serve_transport(source, sink.clone()));
+
+    let actions = sink.actions();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].0, "send_message");
+    assert_eq!(actions[0].1["user_id"], "123456788");
+    assert_eq!(
+        actions[0].1["message"][0]["data"]["text"],
+        "OneBot is not a bot"
+    );
+}